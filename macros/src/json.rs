@@ -123,6 +123,12 @@ impl JSONs {
                         #tindalwic::parse::Parse::builder(&mut #ident).push_item((#expr).into())#err;
                     });
                 }
+                Item::Commented { .. } => {
+                    let value = self.value(item, err, tokens);
+                    tokens.extend(quote! {
+                        #tindalwic::parse::Parse::builder(&mut #ident).push_item(#value)#err;
+                    });
+                }
             }
         }
     }
@@ -130,7 +136,11 @@ impl JSONs {
         let tindalwic = tindalwic();
         let ident = &self.arena.name.ident;
         for entry in dict {
-            let Entry { key, item } = entry;
+            let Entry { before, key, item } = entry;
+            if before.is_some() || matches!(item, Item::Commented { .. }) {
+                self.commented(before.as_deref(), key, item, err, tokens);
+                continue;
+            }
             match item {
                 Item::Text(text) => {
                     tokens.extend(quote! {
@@ -156,6 +166,77 @@ impl JSONs {
                         #tindalwic::parse::Parse::builder(&mut #ident).associate(#key, (#expr).into())#err;
                     });
                 }
+                Item::Commented { .. } => unreachable!("built by commented"),
+            }
+        }
+    }
+}
+impl JSONs {
+    /// the builder's `*_entry` methods have no metadata, so build the item first.
+    fn commented(
+        &self,
+        before: Option<&str>,
+        key: &TokenStream,
+        item: &Item,
+        err: &Propagate,
+        tokens: &mut TokenStream,
+    ) {
+        let tindalwic = tindalwic();
+        let ident = &self.arena.name.ident;
+        let built = Variable::hidden("item");
+        let value = self.value(item, err, tokens);
+        let before = before.map(|before| quote!(.with_before(#before)));
+        tokens.extend(quote! {
+            let #built: #tindalwic::Item = #value;
+            #tindalwic::parse::Parse::builder(&mut #ident)
+                .push_entry(#tindalwic::Entry::new(#key, #built)#before)#err;
+        });
+    }
+    /// an expr for the whole [Item] (its children are pushed to the arena first), for
+    /// where the builder's `*_item` and `*_entry` methods have no room for comments.
+    fn value(&self, item: &Item, err: &Propagate, tokens: &mut TokenStream) -> TokenStream {
+        let tindalwic = tindalwic();
+        let ident = &self.arena.name.ident;
+        match item {
+            Item::Text(text) => quote!(#tindalwic::Item::text(#text)),
+            Item::List(list) => {
+                self.list(list, err, tokens);
+                let count = list.len();
+                quote! {
+                    #tindalwic::Item::list(
+                        #tindalwic::parse::Parse::builder(&mut #ident).finish_items(#count)#err
+                    )
+                }
+            }
+            Item::Dict(dict) => {
+                self.dict(dict, err, tokens);
+                let count = dict.len();
+                quote! {
+                    #tindalwic::Item::dict(
+                        #tindalwic::parse::Parse::builder(&mut #ident).finish_entries(#count)#err
+                    )
+                }
+            }
+            Item::Expr(expr) => quote!((#expr).into()),
+            Item::Commented {
+                item,
+                prolog,
+                epilog,
+            } => {
+                let value = self.value(item, err, tokens);
+                let built = Variable::hidden("commented");
+                tokens.extend(quote!(let mut #built: #tindalwic::Item = #value;));
+                if let Some(prolog) = prolog {
+                    tokens.extend(quote! {
+                        #built.set_prolog(#tindalwic::Comment::some(#prolog))#err;
+                    });
+                }
+                if let Some(epilog) = epilog {
+                    tokens.extend(quote! {
+                        #built.set_epilog(#tindalwic::Comment::some(#epilog));
+                    });
+                }
+                quote!(#built)
             }
         }
     }
@@ -330,6 +330,7 @@ impl Arena {
         match item {
             Item::List(list) => self.count_list(list),
             Item::Dict(dict) => self.count_dict(dict),
+            Item::Commented { item, .. } => self.count_item(item),
             _ => {}
         }
     }
@@ -358,9 +359,24 @@ enum Item {
     List(Punctuated<Item, Token![,]>),
     Dict(Punctuated<Entry, Token![,]>),
     Expr(TokenStream),
+    /// any of the above with its prolog and/or epilog (each an expr for a `&str`).
+    Commented {
+        item: Box<Item>,
+        prolog: Option<TokenStream>,
+        epilog: Option<TokenStream>,
+    },
 }
 impl Parse for Item {
     fn parse(input: ParseStream) -> Result<Self> {
+        let comments: Comments = input.parse()?;
+        if let Some(line) = comments.before.first() {
+            return Err(Error::new(line.span(), "/// comments only before a key"));
+        }
+        comments.attach(Item::parse_uncommented(input)?)
+    }
+}
+impl Item {
+    fn parse_uncommented(input: ParseStream) -> Result<Self> {
         if let Some(stream) = Group::optional_bracketed(input)? {
             Ok(Item::List(stream.punctuated()?))
         } else if let Some(stream) = Group::optional_braced(input)? {
@@ -380,12 +396,90 @@ impl Parse for Item {
     }
 }
 
+/// the attributes in front of an item: `#[prolog = ..]` and `#[epilog = ..]` for
+/// the comments of the item, and in front of a key `///` lines for its `before`.
+struct Comments {
+    before: Vec<syn::LitStr>,
+    prolog: Option<TokenStream>,
+    epilog: Option<TokenStream>,
+}
+impl Parse for Comments {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut comments = Comments {
+            before: Vec::new(),
+            prolog: None,
+            epilog: None,
+        };
+        for attr in input.call(syn::Attribute::parse_outer)? {
+            if let Some(line) = doc_line(&attr) {
+                comments.before.push(line);
+                continue;
+            }
+            let slot = match &attr.meta {
+                syn::Meta::NameValue(meta) if meta.path.is_ident("prolog") => &mut comments.prolog,
+                syn::Meta::NameValue(meta) if meta.path.is_ident("epilog") => &mut comments.epilog,
+                _ => {
+                    let message = "only ///, #[prolog = ..] and #[epilog = ..] allowed here";
+                    return Err(Error::new_spanned(attr, message));
+                }
+            };
+            if slot.is_some() {
+                return Err(Error::new_spanned(attr, "that comment is already given"));
+            }
+            if let syn::Meta::NameValue(meta) = &attr.meta {
+                *slot = Some(meta.value.to_token_stream());
+            }
+        }
+        Ok(comments)
+    }
+}
+impl Comments {
+    /// `item` with the prolog and epilog (the `before` is for the caller).
+    fn attach(self, item: Item) -> Result<Item> {
+        let Comments { prolog, epilog, .. } = self;
+        if let (Some(prolog), Item::Text(_)) = (&prolog, &item) {
+            return Err(Error::new_spanned(prolog, "a text has no prolog"));
+        }
+        if prolog.is_none() && epilog.is_none() {
+            return Ok(item);
+        }
+        Ok(Item::Commented {
+            item: Box::new(item),
+            prolog,
+            epilog,
+        })
+    }
+}
+
+/// `///` lines in front of an entry become its `before` comment (the compiler has
+/// already turned them into `#[doc = "..."]` attributes by the time we see them).
+/// `#[prolog = ..]` and `#[epilog = ..]` there are for the comments of its item.
 struct Entry {
+    before: Option<String>,
     key: TokenStream,
     item: Item,
 }
+fn doc_line(attr: &syn::Attribute) -> Option<syn::LitStr> {
+    let syn::Meta::NameValue(meta) = &attr.meta else {
+        return None;
+    };
+    let syn::Expr::Lit(syn::ExprLit {
+        lit: syn::Lit::Str(doc),
+        ..
+    }) = &meta.value
+    else {
+        return None;
+    };
+    let line = doc.value();
+    let line = line.strip_prefix(' ').unwrap_or(&line);
+    let line = syn::LitStr::new(line, doc.span());
+    meta.path.is_ident("doc").then_some(line)
+}
 impl Parse for Entry {
     fn parse(input: ParseStream) -> Result<Self> {
+        let comments: Comments = input.parse()?;
+        let lines: Vec<String> = comments.before.iter().map(syn::LitStr::value).collect();
+        let before = (!lines.is_empty()).then(|| lines.join("\n"));
         let mut key = TokenStream::new();
         while !input.peek(Token![:]) {
             if input.is_empty() {
@@ -398,8 +492,9 @@ impl Parse for Entry {
         }
         input.parse::<Token![:]>()?;
         Ok(Entry {
+            before,
             key,
-            item: input.parse()?,
+            item: comments.attach(input.parse()?)?,
         })
     }
 }
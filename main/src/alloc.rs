@@ -2,8 +2,16 @@
 
 extern crate alloc;
 
-use crate::Value;
-use alloc::string::String;
+use crate::cells::{Dict, List};
+use crate::fmt::{Chunks, EncodeOptions, Formatted, Fragment};
+use crate::parse::Build;
+use crate::walk::{Branch, Path};
+use crate::{Comment, Entries, Entry, File, Item, Value};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt::Write;
+use core::hash::{Hash, Hasher};
 
 impl<'a> Value<'a> {
     /// Allocates a [String], filled with the UTF-8 copied from `self`.
@@ -25,6 +33,955 @@ impl<'a> Value<'a> {
     }
 }
 
+impl<'a> File<'a> {
+    /// the Tindalwic encoding (same as the [Display](core::fmt::Display) output).
+    pub fn encode(&self) -> String {
+        self.to_string()
+    }
+    /// the Tindalwic encoding, in borrowed slices rather than one allocation.
+    pub fn encode_chunks(&self) -> Chunks<'a> {
+        Chunks::new(*self)
+    }
+    /// the Tindalwic encoding with [EncodeOptions::canonical].
+    pub fn encode_canonical(&self) -> String {
+        self.encode_with(EncodeOptions::canonical())
+    }
+    /// the Tindalwic encoding, formatted according to `options`.
+    pub fn encode_with(&self, options: EncodeOptions) -> String {
+        Formatted {
+            file: *self,
+            options,
+        }
+        .to_string()
+    }
+}
+
+/// encodes one file after another into the same buffer, so once it has grown to fit
+/// the largest, encoding does not allocate.
+#[derive(Clone, Debug, Default)]
+pub struct Encoder {
+    buffer: String,
+    /// how each file is formatted.
+    pub options: EncodeOptions,
+}
+impl Encoder {
+    /// an empty buffer, that will format according to `options`.
+    pub fn new(options: EncodeOptions) -> Self {
+        Encoder {
+            buffer: String::new(),
+            options,
+        }
+    }
+    /// the encoding of `file`, valid until the next call.
+    pub fn encode(&mut self, file: &File<'_>) -> &str {
+        self.buffer.clear();
+        let formatted = Formatted {
+            file: *file,
+            options: self.options,
+        };
+        write!(self.buffer, "{formatted}").expect("write! to a String");
+        &self.buffer
+    }
+}
+
+/// a hash table over the keys of an [Entries], for `O(1)` lookups in a big dict
+/// (rather than [Value::find_linearly_in], which is `O(n)`).
+///
+/// the positions are found when the index is made. an edit that replaces the slice
+/// (e.g. [Dict::insert]) needs a new index, and so does a changed key (e.g.
+/// [Dict::rename]), because a key that moved is not found. every hit is checked,
+/// so a stale index never gives the wrong entry.
+#[derive(Clone, Debug)]
+pub struct KeyIndex<'a> {
+    cells: Entries<'a>,
+    slots: Vec<usize>, // position in cells, or EMPTY
+}
+const EMPTY: usize = usize::MAX;
+impl<'a> KeyIndex<'a> {
+    /// index the keys of `cells` (the first one wins, if a key is repeated).
+    pub fn new(cells: Entries<'a>) -> Self {
+        let mut index = KeyIndex {
+            cells,
+            slots: alloc::vec![EMPTY; (cells.len() * 2).next_power_of_two()],
+        };
+        for (at, cell) in cells.iter().enumerate() {
+            let key = cell.get().key;
+            let mut slot = index.first_slot(&key);
+            loop {
+                match index.slots[slot] {
+                    EMPTY => {
+                        index.slots[slot] = at;
+                        break;
+                    }
+                    other if cells[other].get().key == key => break,
+                    _ => slot = (slot + 1) & (index.slots.len() - 1),
+                }
+            }
+        }
+        index
+    }
+    /// the slice that was indexed.
+    pub fn cells(&self) -> Entries<'a> {
+        self.cells
+    }
+    /// the position of the entry with `key`, like [Value::find_linearly_in].
+    pub fn position(&self, key: Value<'_>) -> Option<usize> {
+        let mut slot = self.first_slot(&key);
+        loop {
+            match self.slots[slot] {
+                EMPTY => return None,
+                at if self.cells.get(at)?.get().key == key => return Some(at),
+                _ => slot = (slot + 1) & (self.slots.len() - 1),
+            }
+        }
+    }
+    /// the cell of the entry with `key`.
+    pub fn get(&self, key: &str) -> Option<&'a Cell<Entry<'a>>> {
+        let cells = self.cells;
+        Some(&cells[self.position(key.into())?])
+    }
+    fn first_slot(&self, key: &Value<'_>) -> usize {
+        let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+        key.hash(&mut hasher);
+        hasher.finish() as usize & (self.slots.len() - 1)
+    }
+}
+
+/// the steps of a [Path] that are only known at run time, e.g. typed by a user.
+///
+/// the text form is a `.key` or an `[index]` for each step, so `.servers[0].port`
+/// is the `port` of the first item in the list `servers`. in a key, a `\` makes the
+/// next character part of the key, for a key with a `.`, `[` or `\` in it (so
+/// `.a\.b` is the one key `a.b`). any other character is taken as it is, and an
+/// empty key (`.` alone) is allowed.
+///
+/// it can also be built one step at a time, starting from [PathBuf::new], or from
+/// a path! (see the [From] impl), and then turned into a [Path] to walk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathBuf<'p> {
+    branches: Vec<Branch<'p>>, // never empty, the last one is Text|List|Dict
+}
+impl<'p> PathBuf<'p> {
+    /// no steps yet, for a path that ends with `lands` (Text, List or Dict). panics
+    /// for anything else, like [Path::new].
+    pub fn new(lands: Branch<'p>) -> Self {
+        assert!(
+            matches!(lands, Branch::Text | Branch::List | Branch::Dict),
+            "path must end with: Text|List|Dict"
+        );
+        PathBuf {
+            branches: alloc::vec![lands],
+        }
+    }
+    /// the steps (each an Item or Entry), without the Text|List|Dict at the end.
+    pub fn steps(&self) -> &[Branch<'p>] {
+        &self.branches[..self.branches.len() - 1]
+    }
+    /// add a step at the end (before the Text|List|Dict). panics unless `step` is a
+    /// Branch::Item or Branch::Entry.
+    pub fn push(&mut self, step: Branch<'p>) {
+        assert!(
+            matches!(step, Branch::Item(_) | Branch::Entry(_)),
+            "Text|List|Dict can only be at end of path"
+        );
+        self.branches.insert(self.branches.len() - 1, step);
+    }
+    /// the last step (an Item or Entry), None if there are none.
+    pub fn last(&self) -> Option<&Branch<'p>> {
+        self.steps().last()
+    }
+    /// the path to the list or dict that holds the last step (ending with List or
+    /// Dict to match), None if there are no steps. with [PathBuf::last], that is
+    /// the container and the key (or index) in it.
+    pub fn parent(&self) -> Option<PathBuf<'p>> {
+        let (last, steps) = self.steps().split_last()?;
+        let mut branches = steps.to_vec();
+        branches.push(match last {
+            Branch::Item(_) => Branch::List,
+            _ => Branch::Dict,
+        });
+        Some(PathBuf { branches })
+    }
+    /// remove the last step, None if there are none.
+    pub fn pop(&mut self) -> Option<Branch<'p>> {
+        let last = self.branches.len().checked_sub(2)?;
+        Some(self.branches.remove(last))
+    }
+    /// the steps of `self` then those of `other`, ending where `other` ends.
+    pub fn join(&self, other: &PathBuf<'p>) -> PathBuf<'p> {
+        let mut branches = self.steps().to_vec();
+        branches.extend_from_slice(&other.branches);
+        PathBuf { branches }
+    }
+    /// `true` if the steps of `prefix` are the first steps of `self` (whatever
+    /// either one ends with).
+    pub fn starts_with(&self, prefix: &PathBuf<'_>) -> bool {
+        self.steps().starts_with(prefix.steps())
+    }
+    /// read `text`, for a path that ends with `lands` (Text, List or Dict, like the
+    /// last word of path!). a key with an escape is unescaped into the `arena`, the
+    /// others are borrowed from `text`.
+    pub fn parse(
+        arena: &mut dyn Build<'p>,
+        text: &'p str,
+        lands: Branch<'p>,
+    ) -> Result<Self, &'static str> {
+        if !matches!(lands, Branch::Text | Branch::List | Branch::Dict) {
+            return Err("path must end with: Text|List|Dict");
+        }
+        let mut branches = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let (branch, after) = path_step(arena, rest)?;
+            branches.push(branch);
+            rest = after;
+        }
+        if branches.is_empty() {
+            return Err("empty path");
+        }
+        branches.push(lands);
+        Ok(PathBuf { branches })
+    }
+    /// all the decisions, the last one is always Text|List|Dict.
+    pub fn branches(&self) -> &[Branch<'p>] {
+        &self.branches
+    }
+    /// the [Path] to walk, if the last step is an `[index]`.
+    pub fn item_path(&self) -> Option<Path<'_, false>> {
+        let last = self.steps().last()?;
+        matches!(last, Branch::Item(_)).then(|| Path::<false>::new(&self.branches))
+    }
+    /// the [Path] to walk, if the last step is a `.key`.
+    pub fn entry_path(&self) -> Option<Path<'_, true>> {
+        let last = self.steps().last()?;
+        matches!(last, Branch::Entry(_)).then(|| Path::<true>::new(&self.branches))
+    }
+}
+impl<'a> PathBuf<'a> {
+    /// put `item` at the end of the path, in place of whatever is there (comments
+    /// too), like `mkdir -p` for any parents that are missing, as far as `scaffold`
+    /// allows. a parent is made as a dict for a `.key` step, or a list for an
+    /// `[index]`. `item` must be what the path ends with (Text, List or Dict).
+    ///
+    /// a new slice is made (in the `arena`) only where an entry or item is added,
+    /// everything else is set in place. so `file` is changed only if a key is added
+    /// to the outermost entries.
+    pub fn set(
+        &self,
+        arena: &mut dyn Build<'a>,
+        file: &mut File<'a>,
+        item: Item<'a>,
+        scaffold: Scaffold,
+    ) -> Result<(), &'static str> {
+        self.check_lands(item)?;
+        if self.steps().is_empty() {
+            return Err("empty path");
+        }
+        let root = Some(Item::dict(file.cells));
+        let root = set_at(arena, root, self.steps(), item, scaffold)?;
+        file.cells = root.as_dict().expect("the root stays a dict");
+        Ok(())
+    }
+}
+impl<'a> PathBuf<'a> {
+    /// take out the entry (with its gap and comments) that the path ends at, from
+    /// the dict that holds it, which gets a new slice (in the `arena`). fails, and
+    /// changes nothing, if the path does not end at an entry of the right type.
+    pub fn remove_entry(
+        &self,
+        arena: &mut dyn Build<'a>,
+        file: &mut File<'a>,
+    ) -> Result<Entry<'a>, &'static str> {
+        let (holder, parent, last) = self.parent_of(file)?;
+        let (
+            Branch::Entry(key),
+            Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            },
+        ) = (last, parent)
+        else {
+            return Err("path must end within an entry in a dict");
+        };
+        let at = key.find_linearly_in(cells).ok_or("key not found")?;
+        self.check_lands(cells[at].get().item)?;
+        let (cells, removed) = cells.remove(arena, key)?;
+        holder.store(
+            file,
+            Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            },
+        );
+        Ok(removed.expect("found above"))
+    }
+    /// take out the item (with its comments) that the path ends at, from the list
+    /// that holds it, which gets a new slice (in the `arena`). fails, and changes
+    /// nothing, if the path does not end at an item of the right type.
+    pub fn remove_item(
+        &self,
+        arena: &mut dyn Build<'a>,
+        file: &mut File<'a>,
+    ) -> Result<Item<'a>, &'static str> {
+        let (holder, parent, last) = self.parent_of(file)?;
+        let (
+            Branch::Item(at),
+            Item::List {
+                prolog,
+                cells,
+                epilog,
+            },
+        ) = (last, parent)
+        else {
+            return Err("path must end within an item in a list");
+        };
+        self.check_lands(cells.get(at).ok_or("index out of bounds")?.get())?;
+        let (cells, removed) = cells.remove(arena, at)?;
+        holder.store(
+            file,
+            Item::List {
+                prolog,
+                cells,
+                epilog,
+            },
+        );
+        Ok(removed)
+    }
+    fn check_lands(&self, item: Item<'_>) -> Result<(), &'static str> {
+        let lands = match item {
+            Item::Text { .. } => Branch::Text,
+            Item::List { .. } => Branch::List,
+            Item::Dict { .. } => Branch::Dict,
+        };
+        if self.branches.last() == Some(&lands) {
+            Ok(())
+        } else {
+            Err("wrong type of item")
+        }
+    }
+    /// where the item that holds the last step is kept, that item, and the last step.
+    fn parent_of(
+        &self,
+        file: &File<'a>,
+    ) -> Result<(Holder<'a>, Item<'a>, Branch<'a>), &'static str> {
+        let (last, steps) = self.steps().split_last().ok_or("empty path")?;
+        let mut holder = Holder::Root;
+        let mut parent = Item::dict(file.cells);
+        for step in steps {
+            match (*step, parent) {
+                (Branch::Entry(key), Item::Dict { cells, .. }) => {
+                    let cell = &cells[key.find_linearly_in(cells).ok_or("key not found")?];
+                    holder = Holder::Entry(cell);
+                    parent = cell.get().item;
+                }
+                (Branch::Item(at), Item::List { cells, .. }) => {
+                    let cell = cells.get(at).ok_or("index out of bounds")?;
+                    holder = Holder::Item(cell);
+                    parent = cell.get();
+                }
+                _ => return Err("wrong type of item"),
+            }
+        }
+        Ok((holder, parent, *last))
+    }
+}
+enum Holder<'a> {
+    Root,
+    Entry(&'a Cell<Entry<'a>>),
+    Item(&'a Cell<Item<'a>>),
+}
+impl<'a> Holder<'a> {
+    fn store(self, file: &mut File<'a>, item: Item<'a>) {
+        match self {
+            Holder::Root => file.cells = item.as_dict().expect("the root is a dict"),
+            Holder::Entry(cell) => {
+                let mut entry = cell.get();
+                entry.item = item;
+                cell.set(entry);
+            }
+            Holder::Item(cell) => cell.set(item),
+        }
+    }
+}
+/// which parents [PathBuf::set] may add, if they are missing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scaffold {
+    /// none: a missing key or index is an error, as for a walk.
+    #[default]
+    Nothing,
+    /// entries, for missing keys (and dicts for them to be in).
+    Dicts,
+    /// entries and list items too. a list that is too short is padded with empty
+    /// texts up to the index.
+    Everything,
+}
+/// `parent` is None where it is missing, to be made (if `scaffold` allows).
+fn set_at<'a>(
+    arena: &mut dyn Build<'a>,
+    parent: Option<Item<'a>>,
+    steps: &[Branch<'a>],
+    item: Item<'a>,
+    scaffold: Scaffold,
+) -> Result<Item<'a>, &'static str> {
+    let (step, rest) = steps.split_first().expect("never called without steps");
+    let inner = |arena: &mut dyn Build<'a>, old: Option<Item<'a>>| {
+        if rest.is_empty() {
+            Ok(item)
+        } else {
+            set_at(arena, old, rest, item, scaffold)
+        }
+    };
+    let parent = match (parent, *step, scaffold) {
+        (Some(parent), _, _) => parent,
+        (None, Branch::Entry(_), Scaffold::Dicts | Scaffold::Everything) => Item::dict(&[]),
+        (None, Branch::Item(_), Scaffold::Everything) => Item::list(&[]),
+        (None, Branch::Entry(_), _) => return Err("key not found"),
+        (None, _, _) => return Err("index out of bounds"),
+    };
+    match (*step, parent) {
+        (
+            Branch::Entry(key),
+            Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            },
+        ) => {
+            if let Some(at) = key.find_linearly_in(cells) {
+                let mut entry = cells[at].get();
+                entry.item = inner(arena, Some(entry.item))?;
+                cells[at].set(entry);
+                return Ok(parent);
+            }
+            if scaffold == Scaffold::Nothing {
+                return Err("key not found");
+            }
+            for cell in cells {
+                arena.push_entry(cell.get())?;
+            }
+            let item = inner(arena, None)?;
+            arena.push_entry(Entry {
+                key,
+                item,
+                ..Entry::default()
+            })?;
+            let cells = arena.finish_entries(cells.len() + 1)?;
+            Ok(Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            })
+        }
+        (
+            Branch::Item(at),
+            Item::List {
+                prolog,
+                cells,
+                epilog,
+            },
+        ) => {
+            if let Some(cell) = cells.get(at) {
+                cell.set(inner(arena, Some(cell.get()))?);
+                return Ok(parent);
+            }
+            if scaffold != Scaffold::Everything {
+                return Err("index out of bounds");
+            }
+            for cell in cells {
+                arena.push_item(cell.get())?;
+            }
+            for _ in cells.len()..at {
+                arena.push_item(Item::default())?;
+            }
+            let item = inner(arena, None)?;
+            arena.push_item(item)?;
+            let cells = arena.finish_items(at + 1)?;
+            Ok(Item::List {
+                prolog,
+                cells,
+                epilog,
+            })
+        }
+        _ => Err("wrong type of item"),
+    }
+}
+impl<'p, const ENTRY: bool> From<&Path<'p, ENTRY>> for PathBuf<'p> {
+    fn from(path: &Path<'p, ENTRY>) -> Self {
+        PathBuf {
+            branches: path.branches().to_vec(),
+        }
+    }
+}
+/// a [PathBuf] with wildcards, to find every item whose path matches.
+///
+/// the text form is that of a [PathBuf], plus two more steps: `.*` (or `[*]`) for
+/// any one key or index, and `.**` for any number of steps (none too). so
+/// `.services.*.image` finds the `image` in each of the `services`, and `.**.port`
+/// finds every `port`, at any depth. write `.\*` for a key that is just `*`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathPattern<'p> {
+    steps: Vec<Step<'p>>,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Step<'p> {
+    Branch(Branch<'p>),
+    One,
+    Any,
+}
+impl<'p> PathPattern<'p> {
+    /// read `text` (see [PathBuf::parse], which this follows for keys and indexes).
+    pub fn parse(arena: &mut dyn Build<'p>, text: &'p str) -> Result<Self, &'static str> {
+        let mut steps = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let wild = [(".**", Step::Any), (".*", Step::One), ("[*]", Step::One)]
+                .into_iter()
+                .find(|(spelling, _)| {
+                    rest.strip_prefix(spelling)
+                        .is_some_and(|after| after.is_empty() || after.starts_with(['.', '[']))
+                });
+            if let Some((spelling, step)) = wild {
+                steps.push(step);
+                rest = &rest[spelling.len()..];
+                continue;
+            }
+            let (branch, after) = path_step(arena, rest)?;
+            steps.push(Step::Branch(branch));
+            rest = after;
+        }
+        if steps.is_empty() {
+            return Err("empty path");
+        }
+        Ok(PathPattern { steps })
+    }
+    /// every item in `file` whose path matches, in the order of [File::walk], with
+    /// that path (ending with what the item is, so it can be walked again).
+    pub fn matches<'a>(&self, file: &File<'a>) -> impl Iterator<Item = (PathBuf<'a>, Item<'a>)> {
+        let mut hits = Vec::new();
+        let mut branches = Vec::new();
+        file.walk(&mut |trail, item| {
+            branches.clear();
+            let mut up = Some(trail);
+            while let Some(trail) = up {
+                branches.push(trail.branch);
+                up = trail.up;
+            }
+            branches.reverse();
+            if glob(&self.steps, &branches) {
+                branches.push(match item {
+                    Item::Text { .. } => Branch::Text,
+                    Item::List { .. } => Branch::List,
+                    Item::Dict { .. } => Branch::Dict,
+                });
+                hits.push((
+                    PathBuf {
+                        branches: branches.clone(),
+                    },
+                    item,
+                ));
+            }
+        });
+        hits.into_iter()
+    }
+}
+fn glob(pattern: &[Step<'_>], branches: &[Branch<'_>]) -> bool {
+    match pattern.split_first() {
+        None => branches.is_empty(),
+        Some((Step::Any, rest)) => (0..=branches.len()).any(|skip| glob(rest, &branches[skip..])),
+        Some((step, rest)) => match branches.split_first() {
+            None => false,
+            Some((branch, more)) => {
+                let accepts = match step {
+                    Step::Branch(step) => step == branch,
+                    _ => true,
+                };
+                accepts && glob(rest, more)
+            }
+        },
+    }
+}
+
+/// the `.key` or `[index]` at the start of `text` (which is not empty), and the
+/// rest after it.
+fn path_step<'p>(
+    arena: &mut dyn Build<'p>,
+    text: &'p str,
+) -> Result<(Branch<'p>, &'p str), &'static str> {
+    match text.as_bytes()[0] {
+        b'.' => {
+            let (key, rest) = path_key(arena, &text[1..])?;
+            Ok((Branch::Entry(key.into()), rest))
+        }
+        b'[' => {
+            let end = text.find(']').ok_or("missing `]` after `[index`")?;
+            let digits = &text[1..end];
+            if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+                return Err("malformed `[index]` in path");
+            }
+            let index = digits.parse().map_err(|_| "`[index]` too big")?;
+            Ok((Branch::Item(index), &text[end + 1..]))
+        }
+        _ => Err("expected `.key` or `[index]` in path"),
+    }
+}
+/// the key at the start of `text`, and the rest after it.
+fn path_key<'p>(
+    arena: &mut dyn Build<'p>,
+    text: &'p str,
+) -> Result<(&'p str, &'p str), &'static str> {
+    let end = text.find(['.', '[', '\\']).unwrap_or(text.len());
+    if !text[end..].starts_with('\\') {
+        return Ok((&text[..end], &text[end..]));
+    }
+    let mut key = String::new();
+    let mut chars = text.char_indices();
+    while let Some((at, char)) = chars.next() {
+        match char {
+            '\\' => key.push(chars.next().ok_or("nothing after `\\` in path")?.1),
+            '.' | '[' => return Ok((arena.intern(&key)?, &text[at..])),
+            _ => key.push(char),
+        }
+    }
+    Ok((arena.intern(&key)?, ""))
+}
+
+/// FNV-1a, which is plenty for keys that are not chosen by an attacker.
+struct Fnv(u64);
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+impl<'a> Item<'a> {
+    /// the encoding as a list member at `indent` (see [Fragment]).
+    pub fn encode_fragment(&self, indent: usize) -> String {
+        Fragment {
+            item: *self,
+            indent,
+        }
+        .to_string()
+    }
+}
+
+impl<'a> Item<'a> {
+    /// add `line` to an Item::Text, so it becomes line number `index` (counting from
+    /// zero, so `index` can be one past the last line). the new value goes in the
+    /// `arena` (see [Item::set_text]). an empty text counts as no lines.
+    pub fn insert_line(
+        &mut self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+        line: &str,
+    ) -> Result<(), &'static str> {
+        self.edit_lines(arena, |lines| {
+            if index > lines.len() {
+                return Err("no line at that index");
+            }
+            lines.insert(index, line);
+            Ok(())
+        })
+    }
+    /// take out line number `index` (see [Item::insert_line]).
+    pub fn remove_line(
+        &mut self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+    ) -> Result<(), &'static str> {
+        self.edit_lines(arena, |lines| {
+            if index >= lines.len() {
+                return Err("no line at that index");
+            }
+            lines.remove(index);
+            Ok(())
+        })
+    }
+    /// change line number `index` to `line` (see [Item::insert_line]).
+    pub fn replace_line(
+        &mut self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+        line: &str,
+    ) -> Result<(), &'static str> {
+        self.edit_lines(arena, |lines| {
+            let Some(old) = lines.get_mut(index) else {
+                return Err("no line at that index");
+            };
+            *old = line;
+            Ok(())
+        })
+    }
+    /// add `line` after the last line (see [Item::insert_line]).
+    pub fn push_line(&mut self, arena: &mut dyn Build<'a>, line: &str) -> Result<(), &'static str> {
+        self.extend_lines(arena, [line])
+    }
+    /// add each of `lines` after the last line (see [Item::insert_line]), making only
+    /// one new value for all of them.
+    pub fn extend_lines<'l>(
+        &mut self,
+        arena: &mut dyn Build<'a>,
+        lines: impl IntoIterator<Item = &'l str>,
+    ) -> Result<(), &'static str>
+    where
+        'a: 'l,
+    {
+        self.edit_lines(arena, |old| {
+            old.extend(lines);
+            Ok(())
+        })
+    }
+    fn edit_lines<'l>(
+        &mut self,
+        arena: &mut dyn Build<'a>,
+        edit: impl FnOnce(&mut Vec<&'l str>) -> Result<(), &'static str>,
+    ) -> Result<(), &'static str>
+    where
+        'a: 'l,
+    {
+        let value = self.as_text().ok_or("not a text")?;
+        let mut lines: Vec<&'l str> = if value.is_empty() {
+            Vec::new()
+        } else {
+            value.lines().collect()
+        };
+        edit(&mut lines)?;
+        self.set_text(arena, &lines.join("\n"))
+    }
+}
+
+impl<'a> Comment<'a> {
+    /// a comment made of `lines` (with no `\n` needed between them), in the `arena`
+    /// (see [Comment::interned]). no lines at all is the same as one empty line.
+    pub fn from_lines<'l>(
+        arena: &mut dyn Build<'a>,
+        lines: impl IntoIterator<Item = &'l str>,
+    ) -> Result<Self, &'static str> {
+        let lines: Vec<&str> = lines.into_iter().collect();
+        Comment::interned(arena, &lines.join("\n"))
+    }
+    /// a comment made by [format!](alloc::format), e.g. from `format_args!("by {who}")`.
+    pub fn from_fmt(
+        arena: &mut dyn Build<'a>,
+        args: core::fmt::Arguments<'_>,
+    ) -> Result<Self, &'static str> {
+        Comment::interned(arena, &alloc::fmt::format(args))
+    }
+    /// add `line` after the last line, in the `arena` (see [Comment::set_text]). a
+    /// comment always has at least one line, so an empty comment keeps an empty
+    /// first line.
+    pub fn push_line(&mut self, arena: &mut dyn Build<'a>, line: &str) -> Result<(), &'static str> {
+        let mut text = self.value.joined();
+        text.push('\n');
+        text.push_str(line);
+        self.set_text(arena, &text)
+    }
+    /// re-wrap the paragraphs to lines of at most `width` chars (except for a word
+    /// that is longer on its own), in the `arena` (see [Comment::set_text]).
+    ///
+    /// only plain paragraphs change. code fences and everything inside them, indented
+    /// code, headings (and the `===` or `---` under one), rules, list items and
+    /// their indented continuation lines, quotes, tables and blank lines stay as
+    /// they are. a `width` of 0 is an error.
+    pub fn reflow(&mut self, arena: &mut dyn Build<'a>, width: usize) -> Result<(), &'static str> {
+        if width == 0 {
+            return Err("width of a line");
+        }
+        let mut result: Vec<String> = Vec::new();
+        let mut words: Vec<&str> = Vec::new();
+        let mut fenced = false;
+        let mut listed = false;
+        for line in self.value.lines() {
+            let trimmed = line.trim_start();
+            let indented = trimmed.len() < line.len();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fenced = !fenced;
+            } else if !fenced {
+                if is_list_item(trimmed) {
+                    listed = true;
+                } else if !indented && !trimmed.is_empty() {
+                    listed = false;
+                }
+                if !(listed && indented) && is_prose(line) {
+                    words.extend(line.split_whitespace());
+                    continue;
+                }
+            }
+            wrap(&mut words, width, &mut result);
+            result.push(String::from(line));
+        }
+        wrap(&mut words, width, &mut result);
+        self.set_text(arena, &result.join("\n"))
+    }
+    /// [Comment::push_line] for any of the comment fields, e.g. to keep an audit
+    /// trail in [Entry::before]: None becomes a comment of just `line`.
+    pub fn append_line(
+        comment: &mut Option<Comment<'a>>,
+        arena: &mut dyn Build<'a>,
+        line: &str,
+    ) -> Result<(), &'static str> {
+        match comment {
+            Some(comment) => comment.push_line(arena, line),
+            None => {
+                *comment = Some(Comment::interned(arena, line)?);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// the start of a header made by [File::set_generated_header] (the usual marker for
+/// generated files, which many tools look for).
+pub const GENERATED: &str = "@generated";
+
+/// `template` with each `{name}` replaced by the value for `name` in `values`, e.g.
+/// to fill in the tool, version or time for [File::set_generated_header]. a name
+/// that is not in `values`, or a `{` without a `}`, is an error.
+pub fn fill_template(template: &str, values: &[(&str, &str)]) -> Result<String, &'static str> {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        result.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else {
+            return Err("{ without }");
+        };
+        let name = &rest[open + 1..open + close];
+        let Some((_, value)) = values.iter().find(|(key, _)| *key == name) else {
+            return Err("unknown placeholder");
+        };
+        result.push_str(value);
+        rest = &rest[open + close + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+impl<'a> File<'a> {
+    /// put `header` at the start of the prolog, in the `arena` (see
+    /// [Comment::set_text]). a header put there before (one that starts with
+    /// [GENERATED], up to the first empty line) is replaced, so doing this again
+    /// with the same header changes nothing. the rest of the prolog is kept, after
+    /// an empty line.
+    ///
+    /// `header` must start with [GENERATED], and have no empty lines.
+    pub fn set_generated_header(
+        &mut self,
+        arena: &mut dyn Build<'a>,
+        header: &str,
+    ) -> Result<(), &'static str> {
+        if !header.starts_with(GENERATED) {
+            return Err("header must start with @generated");
+        }
+        if header.lines().any(|line| line.trim().is_empty()) || header.ends_with('\n') {
+            return Err("header must not have empty lines");
+        }
+        let mut lines: Vec<&str> = match self.prolog {
+            Some(prolog) => prolog.value.lines().collect(),
+            None => Vec::new(),
+        };
+        if lines
+            .first()
+            .is_some_and(|line| line.starts_with(GENERATED))
+        {
+            let end = lines.iter().position(|line| line.is_empty());
+            lines.drain(..end.map_or(lines.len(), |end| end + 1));
+        }
+        let mut text = String::from(header);
+        if !lines.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&lines.join("\n"));
+        }
+        self.prolog = Some(Comment::interned(arena, &text)?);
+        Ok(())
+    }
+}
+
+/// a line of a paragraph, that [Comment::reflow] can join with its neighbours.
+fn is_prose(line: &str) -> bool {
+    if line.trim().is_empty() || line.starts_with("    ") || line.starts_with('\t') {
+        return false;
+    }
+    let line = line.trim_start();
+    !(is_list_item(line) || is_rule(line) || line.starts_with(['#', '>', '|']))
+}
+
+/// the start of a bullet or numbered list item (`line` without its indent).
+fn is_list_item(line: &str) -> bool {
+    let numbered = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let bullet = ["-", "+", "*"].iter().any(|marker| {
+        line == *marker
+            || line
+                .strip_prefix(marker)
+                .is_some_and(|rest| rest.starts_with(' '))
+    });
+    bullet
+        || numbered.len() < line.len() && (numbered.starts_with(". ") || numbered.starts_with(") "))
+}
+
+/// a line of only `=`, `-`, `*` or `_`: the underline of a heading, or a rule.
+fn is_rule(line: &str) -> bool {
+    let line = line.trim_end();
+    ['=', '-', '*', '_']
+        .iter()
+        .any(|mark| !line.is_empty() && line.chars().all(|c| c == *mark))
+}
+
+/// fill lines of at most `width` chars from `words` (emptied) onto `lines`.
+fn wrap(words: &mut Vec<&str>, width: usize, lines: &mut Vec<String>) {
+    let mut line = String::new();
+    for word in words.drain(..) {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(core::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+}
+
+/// the space tolerant reading: turn each run of `width` spaces at the start of a line
+/// back into a tab, so output with [EncodeOptions::expand_tabs] can be parsed again.
+pub fn retab(spaced: &str, width: usize) -> String {
+    assert!(width > 0, "width of a tab");
+    let indent = " ".repeat(width);
+    let mut result = String::with_capacity(spaced.len());
+    for line in spaced.split_inclusive('\n') {
+        let mut remainder = line;
+        while let Some(trailing) = remainder.strip_prefix(indent.as_str()) {
+            result.push('\t');
+            remainder = trailing;
+        }
+        result.push_str(remainder);
+    }
+    result
+}
+
+/// add `entry` (with its gap and `//` comment) after the outermost entries of the
+/// encoded document in `existing`, without decoding or re-encoding any of it.
+pub fn encode_append(entry: &Entry<'_>, existing: &mut String) {
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        existing.push('\n');
+    }
+    existing.push_str(&entry.to_string());
+}
+
+/// the line ending tolerant reading: turn each `\r\n` into `\n`, so output with
+/// [LineEnding::CrLf](crate::fmt::LineEnding::CrLf) (or a file edited on Windows) can
+/// be parsed. a `\r` that is not followed by `\n` is text, and stays.
+pub fn from_crlf(content: &str) -> String {
+    content.replace("\r\n", "\n")
+}
+
 /// turn a formatted Rust source code string literal into tindalwic.
 pub fn from_literal(literal: &'static str) -> String {
     let mut lines = literal.lines().enumerate();
@@ -18,6 +18,13 @@ impl<T: Copy> CellVec<T> {
     fn new() -> Self {
         CellVec(Cell::new(Vec::new()))
     }
+    fn reserve(&self, additional: usize) {
+        let CellVec(cell) = self;
+        // SAFETY: Cell instance is private, no ref to its Vec value leaks outside this
+        // impl, except via this let, only as receiver in Vec methods, which are safe.
+        let vec = unsafe { &mut *cell.as_ptr() };
+        vec.reserve(additional);
+    }
     fn push(&self, value: T) -> Option<()> {
         let CellVec(cell) = self;
         // SAFETY: Cell instance is private, no ref to its Vec value leaks outside this
@@ -84,6 +91,19 @@ impl<'a> Arena<'a> {
         };
         Arena { builder }
     }
+    /// like [Arena::new], with room for this many items and entries to be pushed
+    /// before they are finished (which is all of them, for a flat list or dict).
+    /// the Bump can be pre-sized too, see [Bump::with_capacity].
+    pub fn with_capacity(bump: &'a Bump, items: usize, entries: usize) -> Self {
+        let arena = Arena::new(bump);
+        arena.reserve(items, entries);
+        arena
+    }
+    /// make room for this many more items and entries to be pushed.
+    pub fn reserve(&self, items: usize, entries: usize) {
+        self.builder.items.reserve(items);
+        self.builder.entries.reserve(entries);
+    }
     /// call the parser on the provided content, collect first `count` errors.
     pub fn collect_errors(
         &mut self,
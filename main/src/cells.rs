@@ -0,0 +1,914 @@
+//! methods for the [Items] and [Entries] slices of cells.
+//!
+//! the slices have a fixed length, so anything that produces a different slice needs
+//! a [Build] to put the result into. everything else happens in place via the cells.
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+use crate::parse::Build;
+use crate::seeded::Seeded;
+use crate::walk::{Branch, Path, Trail};
+use crate::{Comment, Entries, Entry, Item, Items, Value};
+use core::cell::Cell;
+use core::cmp::Ordering;
+use core::ops::Range;
+
+/// extra methods for the [Items] slice of an [Item::List].
+pub trait List<'a>: Sized {
+    /// pick `n` of the items, keeping their order. the same `seed` makes the same
+    /// picks every time. returns `self` if it has no more than `n` items.
+    fn sample(
+        self,
+        arena: &mut dyn Build<'a>,
+        n: usize,
+        seed: u64,
+    ) -> Result<Items<'a>, &'static str>;
+    /// keep only what the `paths` lead to (the first branch of each path selects an
+    /// item in this list), along with all the comments on the way there.
+    ///
+    /// the last branch (Text|List|Dict) of each path is not checked.
+    fn take_paths<const ENTRY: bool>(
+        self,
+        arena: &mut dyn Build<'a>,
+        paths: &[Path<'_, ENTRY>],
+    ) -> Result<Items<'a>, &'static str>;
+    /// append a copy of the item at `index` (a deep copy, see [Item::clone_owned], so
+    /// the arena must support [Build::intern]), after each override in the copy: the
+    /// path (starting inside the copy) to an entry, and the item to put there instead.
+    ///
+    /// as in "copy the previous server block and change the host".
+    fn push_cloned_from(
+        self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+        overrides: &[(Path<'_, true>, Item<'a>)],
+    ) -> Result<Items<'a>, &'static str>;
+    /// put `item` (with its comment) at `index`, moving the rest along.
+    fn insert(
+        self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+        item: Item<'a>,
+    ) -> Result<Items<'a>, &'static str>;
+    /// take out the item (with its comment) at `index`, the rest keep their order.
+    fn remove(
+        self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+    ) -> Result<(Items<'a>, Item<'a>), &'static str>;
+    /// exchange the items (with their comments) at `a` and `b`, in place.
+    fn swap_at(self, a: usize, b: usize) -> Result<(), &'static str>;
+    /// the first `len` items (all of them if there are fewer). no copy is needed.
+    fn truncate(self, len: usize) -> Items<'a>;
+    /// keep only the items that `keep` says `true` for, in order.
+    fn retain(
+        self,
+        arena: &mut dyn Build<'a>,
+        keep: impl FnMut(&Item<'a>) -> bool,
+    ) -> Result<Items<'a>, &'static str>;
+    /// take out the items in `range`. returns the rest, and the ones taken out (with
+    /// their comments, still in the cells of `self`, ready to go elsewhere).
+    fn drain(
+        self,
+        arena: &mut dyn Build<'a>,
+        range: Range<usize>,
+    ) -> Result<(Items<'a>, Items<'a>), &'static str>;
+    /// replace the items in `range` with `items` (which must not use the `arena`
+    /// while it runs).
+    fn splice(
+        self,
+        arena: &mut dyn Build<'a>,
+        range: Range<usize>,
+        items: impl IntoIterator<Item = Item<'a>>,
+    ) -> Result<Items<'a>, &'static str>;
+}
+impl<'a> List<'a> for Items<'a> {
+    fn insert(
+        self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+        item: Item<'a>,
+    ) -> Result<Items<'a>, &'static str> {
+        self.splice(arena, index..index, [item])
+    }
+    fn remove(
+        self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+    ) -> Result<(Items<'a>, Item<'a>), &'static str> {
+        let Some(cell) = self.get(index) else {
+            return Err("index out of bounds");
+        };
+        let item = cell.get();
+        Ok((self.splice(arena, index..index + 1, [])?, item))
+    }
+    fn retain(
+        self,
+        arena: &mut dyn Build<'a>,
+        mut keep: impl FnMut(&Item<'a>) -> bool,
+    ) -> Result<Items<'a>, &'static str> {
+        let mut count = 0usize;
+        for cell in self {
+            let item = cell.get();
+            if keep(&item) {
+                arena.push_item(item)?;
+                count += 1;
+            }
+        }
+        finish_items(arena, count)
+    }
+    fn drain(
+        self,
+        arena: &mut dyn Build<'a>,
+        range: Range<usize>,
+    ) -> Result<(Items<'a>, Items<'a>), &'static str> {
+        let rest = self.splice(arena, range.clone(), [])?;
+        Ok((rest, &self[range]))
+    }
+    fn swap_at(self, a: usize, b: usize) -> Result<(), &'static str> {
+        match (self.get(a), self.get(b)) {
+            (Some(a), Some(b)) => {
+                a.swap(b);
+                Ok(())
+            }
+            _ => Err("index out of bounds"),
+        }
+    }
+    fn truncate(self, len: usize) -> Items<'a> {
+        &self[..len.min(self.len())]
+    }
+    fn splice(
+        self,
+        arena: &mut dyn Build<'a>,
+        range: Range<usize>,
+        items: impl IntoIterator<Item = Item<'a>>,
+    ) -> Result<Items<'a>, &'static str> {
+        if range.start > range.end || range.end > self.len() {
+            return Err("index out of bounds");
+        }
+        let mut count = self.len() - range.len();
+        for cell in &self[..range.start] {
+            arena.push_item(cell.get())?;
+        }
+        for item in items {
+            arena.push_item(item)?;
+            count += 1;
+        }
+        for cell in &self[range.end..] {
+            arena.push_item(cell.get())?;
+        }
+        finish_items(arena, count)
+    }
+    fn push_cloned_from(
+        self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+        overrides: &[(Path<'_, true>, Item<'a>)],
+    ) -> Result<Items<'a>, &'static str> {
+        let Some(cell) = self.get(index) else {
+            return Err("no item at that index");
+        };
+        let copy = cell.get().clone_owned(arena)?;
+        for (path, item) in overrides {
+            let cell = path.walk(copy).map_err(|error| error.message)?;
+            let mut entry = cell.get();
+            entry.item = *item;
+            cell.set(entry);
+        }
+        for cell in self {
+            arena.push_item(cell.get())?;
+        }
+        arena.push_item(copy)?;
+        finish_items(arena, self.len() + 1)
+    }
+    fn sample(
+        self,
+        arena: &mut dyn Build<'a>,
+        n: usize,
+        seed: u64,
+    ) -> Result<Items<'a>, &'static str> {
+        if n >= self.len() {
+            return Ok(self);
+        }
+        // selection sampling (Knuth, TAOCP Vol. 2, §3.4.2 Algorithm S)
+        let mut rng = Seeded::new(seed);
+        let mut needed = n;
+        for (at, cell) in self.iter().enumerate() {
+            if needed == 0 {
+                break;
+            }
+            if rng.below(self.len() - at) < needed {
+                arena.push_item(cell.get())?;
+                needed -= 1;
+            }
+        }
+        finish_items(arena, n)
+    }
+    fn take_paths<const ENTRY: bool>(
+        self,
+        arena: &mut dyn Build<'a>,
+        paths: &[Path<'_, ENTRY>],
+    ) -> Result<Items<'a>, &'static str> {
+        match prune(arena, Item::list(self), None, paths)? {
+            Item::List { cells, .. } => Ok(cells),
+            _ => unreachable!("prune never changes the kind of item"),
+        }
+    }
+}
+
+/// extra methods for the [Entries] slice of an [Item::Dict] (or a [File](crate::File)).
+pub trait Dict<'a>: Sized {
+    /// add each entry of `defaults` whose key is missing (after the existing entries,
+    /// in the order of `defaults`), recursing where both sides have a dict for a key.
+    /// the added entries get `note` as their `before` comment (unless it is None).
+    /// returns `self` if nothing is missing.
+    fn fill_defaults(
+        self,
+        arena: &mut dyn Build<'a>,
+        defaults: Entries<'a>,
+        note: Option<Comment<'a>>,
+    ) -> Result<Entries<'a>, &'static str>;
+    /// layer `other` on top: an entry of `other` replaces the item of the entry with
+    /// the same key (whose gap and comment stay), or is added at the end (with its
+    /// gap and comment). with [MergeDepth::Deep], where both items are dicts, they
+    /// are merged the same way instead (keeping the prolog and epilog of `self`).
+    /// no comments are merged here: for that, see [Dict::merge_with_comments].
+    fn merge_from(
+        self,
+        arena: &mut dyn Build<'a>,
+        other: Entries<'a>,
+        depth: MergeDepth,
+    ) -> Result<Entries<'a>, &'static str>;
+    /// like [Dict::merge_from], but where both sides have an entry for a key, each
+    /// of its comments (the `before` of the entry, and the prolog and epilog of the
+    /// item) is picked by `comments`. a prolog is lost only when the item becomes a
+    /// text, which has nowhere to put one.
+    fn merge_with_comments(
+        self,
+        arena: &mut dyn Build<'a>,
+        other: Entries<'a>,
+        depth: MergeDepth,
+        comments: CommentMergePolicy,
+    ) -> Result<Entries<'a>, &'static str>;
+    /// the entry for `key`, or if that is an alias (a text like `!alias:port`, see
+    /// [Value::alias_of]) the entry it stands for, following chains of aliases.
+    /// None if a key is missing, or the aliases go round in a circle.
+    ///
+    /// so a key can be renamed, leaving the old one behind for old readers.
+    fn resolve(self, key: Value<'_>) -> Option<&'a Cell<Entry<'a>>>;
+    /// the keys that [Dict::resolve] visits, starting with `key` itself.
+    fn alias_chain(self, key: Value<'a>) -> AliasChain<'a>;
+    /// put `entry` (with its gap and comment) at `index`, moving the rest along.
+    /// fails if its key is already used.
+    fn insert(
+        self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+        entry: Entry<'a>,
+    ) -> Result<Entries<'a>, &'static str>;
+    /// take out the entry for `key` (with its gap and comment), the rest keep their
+    /// order. returns `self` and None if there is no such entry.
+    fn remove(
+        self,
+        arena: &mut dyn Build<'a>,
+        key: Value<'_>,
+    ) -> Result<(Entries<'a>, Option<Entry<'a>>), &'static str>;
+    /// change the key `old` to `new` in place, keeping the gap, comment and item.
+    /// fails if `old` is missing, or `new` is already used by another entry.
+    fn rename(self, old: Value<'_>, new: Value<'a>) -> Result<(), &'static str>;
+    /// the key of each entry, in order.
+    fn keys(self) -> impl Iterator<Item = Value<'a>>;
+    /// the item of each entry, in order.
+    fn items(self) -> impl Iterator<Item = Item<'a>>;
+    /// the key and item of each entry, in order. (to change one, `set` its cell.)
+    fn pairs(self) -> impl Iterator<Item = (Value<'a>, Item<'a>)>;
+    /// the cell of the entry for `key`, after adding one (at the end, holding what
+    /// `default` makes) if it was missing. also returns the entries, which are `self`
+    /// unless one was added.
+    fn get_or_insert_with(
+        self,
+        arena: &mut dyn Build<'a>,
+        key: Value<'a>,
+        default: impl FnOnce() -> Item<'a>,
+    ) -> Result<(Entries<'a>, &'a Cell<Entry<'a>>), &'static str>;
+    /// keep only the entries that `keep` says `true` for, in order.
+    fn retain(
+        self,
+        arena: &mut dyn Build<'a>,
+        keep: impl FnMut(&Entry<'a>) -> bool,
+    ) -> Result<Entries<'a>, &'static str>;
+    /// take out the entries in `range`. returns the rest, and the ones taken out
+    /// (with their gaps and comments, still in the cells of `self`).
+    fn drain(
+        self,
+        arena: &mut dyn Build<'a>,
+        range: Range<usize>,
+    ) -> Result<(Entries<'a>, Entries<'a>), &'static str>;
+    /// move the entry for `key` (with its gap and comment) to just before the entry
+    /// for `before`, in place.
+    fn move_key_before(self, key: Value<'_>, before: Value<'_>) -> Result<(), &'static str>;
+    /// move the entry for `key` (with its gap and comment) to `index`, in place.
+    fn move_key_to(self, key: Value<'_>, index: usize) -> Result<(), &'static str>;
+    /// order the entries by key (compared line by line, as for
+    /// [EncodeOptions::sort_keys](crate::fmt::EncodeOptions::sort_keys)), in place.
+    fn sort_by_key_name(self);
+    /// give every entry with a `before` comment a gap (except the first entry), in
+    /// place, leaving the others alone. see also [File::normalize_gaps](crate::File::normalize_gaps).
+    fn gap_before_commented_keys(self);
+    /// give the entry for `key` a gap (or not), in place. fails if `key` is missing.
+    fn set_gap(self, key: Value<'_>, gap: bool) -> Result<(), &'static str>;
+    /// order the entries with `compare`, in place. the sort is stable. with the
+    /// "alloc" feature it sorts a copy of the entries, O(n log n). without it, it is
+    /// an insertion sort, O(n^2), which suits only dicts of a modest size.
+    fn sort_entries_by(self, compare: impl FnMut(&Entry<'a>, &Entry<'a>) -> Ordering);
+    /// the entries, then one more for each of the `pairs` (see [collect_entries]).
+    fn extended(
+        self,
+        arena: &mut dyn Build<'a>,
+        pairs: impl IntoIterator<Item = (Value<'a>, Item<'a>)>,
+    ) -> Result<Entries<'a>, &'static str>;
+}
+impl<'a> Dict<'a> for Entries<'a> {
+    fn merge_from(
+        self,
+        arena: &mut dyn Build<'a>,
+        other: Entries<'a>,
+        depth: MergeDepth,
+    ) -> Result<Entries<'a>, &'static str> {
+        merge(self, arena, other, depth, None)
+    }
+    fn merge_with_comments(
+        self,
+        arena: &mut dyn Build<'a>,
+        other: Entries<'a>,
+        depth: MergeDepth,
+        comments: CommentMergePolicy,
+    ) -> Result<Entries<'a>, &'static str> {
+        merge(self, arena, other, depth, Some(comments))
+    }
+    fn retain(
+        self,
+        arena: &mut dyn Build<'a>,
+        mut keep: impl FnMut(&Entry<'a>) -> bool,
+    ) -> Result<Entries<'a>, &'static str> {
+        let mut count = 0usize;
+        for cell in self {
+            let entry = cell.get();
+            if keep(&entry) {
+                arena.push_entry(entry)?;
+                count += 1;
+            }
+        }
+        finish_entries(arena, count)
+    }
+    fn drain(
+        self,
+        arena: &mut dyn Build<'a>,
+        range: Range<usize>,
+    ) -> Result<(Entries<'a>, Entries<'a>), &'static str> {
+        if range.start > range.end || range.end > self.len() {
+            return Err("index out of bounds");
+        }
+        for cell in self[..range.start].iter().chain(&self[range.end..]) {
+            arena.push_entry(cell.get())?;
+        }
+        let rest = finish_entries(arena, self.len() - range.len())?;
+        Ok((rest, &self[range]))
+    }
+    fn get_or_insert_with(
+        self,
+        arena: &mut dyn Build<'a>,
+        key: Value<'a>,
+        default: impl FnOnce() -> Item<'a>,
+    ) -> Result<(Entries<'a>, &'a Cell<Entry<'a>>), &'static str> {
+        if let Some(at) = key.find_linearly_in(self) {
+            return Ok((self, &self[at]));
+        }
+        let entry = Entry {
+            key,
+            item: default(),
+            ..Entry::default()
+        };
+        let cells = self.insert(arena, self.len(), entry)?;
+        Ok((cells, &cells[self.len()]))
+    }
+    fn move_key_before(self, key: Value<'_>, before: Value<'_>) -> Result<(), &'static str> {
+        let (Some(from), Some(to)) = (key.find_linearly_in(self), before.find_linearly_in(self))
+        else {
+            return Err("key not found");
+        };
+        move_cell(self, from, if from < to { to - 1 } else { to });
+        Ok(())
+    }
+    fn move_key_to(self, key: Value<'_>, index: usize) -> Result<(), &'static str> {
+        let Some(from) = key.find_linearly_in(self) else {
+            return Err("key not found");
+        };
+        if index >= self.len() {
+            return Err("index out of bounds");
+        }
+        move_cell(self, from, index);
+        Ok(())
+    }
+    fn set_gap(self, key: Value<'_>, gap: bool) -> Result<(), &'static str> {
+        let Some(at) = key.find_linearly_in(self) else {
+            return Err("key not found");
+        };
+        let mut entry = self[at].get();
+        entry.gap = gap;
+        self[at].set(entry);
+        Ok(())
+    }
+    fn gap_before_commented_keys(self) {
+        for cell in self.iter().skip(1) {
+            let mut entry = cell.get();
+            if entry.before.is_some() {
+                entry.gap = true;
+                cell.set(entry);
+            }
+        }
+    }
+    fn sort_by_key_name(self) {
+        self.sort_entries_by(|a, b| a.key.lines().cmp(b.key.lines()));
+    }
+    #[cfg(feature = "alloc")]
+    fn sort_entries_by(self, compare: impl FnMut(&Entry<'a>, &Entry<'a>) -> Ordering) {
+        let mut entries = alloc::vec::Vec::from_iter(self.iter().map(Cell::get));
+        entries.sort_by(compare);
+        for (cell, entry) in self.iter().zip(entries) {
+            cell.set(entry);
+        }
+    }
+    #[cfg(not(feature = "alloc"))]
+    fn sort_entries_by(self, mut compare: impl FnMut(&Entry<'a>, &Entry<'a>) -> Ordering) {
+        for sorted in 1..self.len() {
+            let mut at = sorted;
+            while at > 0 && compare(&self[at - 1].get(), &self[at].get()) == Ordering::Greater {
+                self[at - 1].swap(&self[at]);
+                at -= 1;
+            }
+        }
+    }
+    fn keys(self) -> impl Iterator<Item = Value<'a>> {
+        self.iter().map(|cell| cell.get().key)
+    }
+    fn items(self) -> impl Iterator<Item = Item<'a>> {
+        self.iter().map(|cell| cell.get().item)
+    }
+    fn extended(
+        self,
+        arena: &mut dyn Build<'a>,
+        pairs: impl IntoIterator<Item = (Value<'a>, Item<'a>)>,
+    ) -> Result<Entries<'a>, &'static str> {
+        let more = self.iter().map(|cell| {
+            let entry = cell.get();
+            (entry.key, entry.item)
+        });
+        let cells = collect_entries(arena, more.chain(pairs))?;
+        // keep the gaps and comments, which the pairs left behind
+        for (cell, old) in cells.iter().zip(self) {
+            cell.set(old.get());
+        }
+        Ok(cells)
+    }
+    fn pairs(self) -> impl Iterator<Item = (Value<'a>, Item<'a>)> {
+        self.iter().map(|cell| {
+            let entry = cell.get();
+            (entry.key, entry.item)
+        })
+    }
+    fn insert(
+        self,
+        arena: &mut dyn Build<'a>,
+        index: usize,
+        entry: Entry<'a>,
+    ) -> Result<Entries<'a>, &'static str> {
+        if index > self.len() {
+            return Err("index out of bounds");
+        }
+        if entry.key.find_linearly_in(self).is_some() {
+            return Err("duplicate key");
+        }
+        for cell in &self[..index] {
+            arena.push_entry(cell.get())?;
+        }
+        arena.push_entry(entry)?;
+        for cell in &self[index..] {
+            arena.push_entry(cell.get())?;
+        }
+        finish_entries(arena, self.len() + 1)
+    }
+    fn remove(
+        self,
+        arena: &mut dyn Build<'a>,
+        key: Value<'_>,
+    ) -> Result<(Entries<'a>, Option<Entry<'a>>), &'static str> {
+        let Some(at) = key.find_linearly_in(self) else {
+            return Ok((self, None));
+        };
+        for (index, cell) in self.iter().enumerate() {
+            if index != at {
+                arena.push_entry(cell.get())?;
+            }
+        }
+        let cells = finish_entries(arena, self.len() - 1)?;
+        Ok((cells, Some(self[at].get())))
+    }
+    fn rename(self, old: Value<'_>, new: Value<'a>) -> Result<(), &'static str> {
+        let Some(at) = old.find_linearly_in(self) else {
+            return Err("key not found");
+        };
+        if new.find_linearly_in(self).is_some_and(|other| other != at) {
+            return Err("duplicate key");
+        }
+        let mut entry = self[at].get();
+        entry.key = new;
+        self[at].set(entry);
+        Ok(())
+    }
+    fn resolve(self, key: Value<'_>) -> Option<&'a Cell<Entry<'a>>> {
+        let mut cell = &self[key.find_linearly_in(self)?];
+        for _ in 0..self.len() {
+            let Some(target) = cell.get().item.alias_of() else {
+                return Some(cell);
+            };
+            cell = &self[Value::from(target).find_linearly_in(self)?];
+        }
+        None
+    }
+    fn alias_chain(self, key: Value<'a>) -> AliasChain<'a> {
+        AliasChain {
+            cells: self,
+            next: Some(key),
+            left: self.len() + 1,
+        }
+    }
+    fn fill_defaults(
+        self,
+        arena: &mut dyn Build<'a>,
+        defaults: Entries<'a>,
+        note: Option<Comment<'a>>,
+    ) -> Result<Entries<'a>, &'static str> {
+        if !lacks(self, defaults) {
+            return Ok(self);
+        }
+        let mut count = 0usize;
+        for cell in self {
+            let mut entry = cell.get();
+            if let Item::Dict { cells, .. } = &mut entry.item {
+                if let Some(at) = entry.key.find_linearly_in(defaults) {
+                    if let Item::Dict {
+                        cells: template, ..
+                    } = defaults[at].get().item
+                    {
+                        *cells = cells.fill_defaults(arena, template, note)?;
+                    }
+                }
+            }
+            arena.push_entry(entry)?;
+            count += 1;
+        }
+        for (at, cell) in defaults.iter().enumerate() {
+            let mut entry = cell.get();
+            if entry.key.find_linearly_in(defaults) != Some(at)
+                || entry.key.find_linearly_in(self).is_some()
+            {
+                continue;
+            }
+            if note.is_some() {
+                entry.before = note;
+            }
+            arena.push_entry(entry)?;
+            count += 1;
+        }
+        finish_entries(arena, count)
+    }
+}
+
+/// how far [Dict::merge_from] (and [Dict::merge_with_comments]) goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeDepth {
+    /// only the entries of the dict itself: an item replaces an item, whatever it is.
+    Shallow,
+    /// into every pair of dicts under the same key.
+    Deep,
+}
+
+/// which comment [Dict::merge_with_comments] keeps where both sides have one.
+/// where only one side has a comment, that one is kept whatever the policy.
+///
+/// only [Dict::merge_with_comments] takes a policy. [Dict::merge_from] keeps the
+/// comments of `self` (and those that come with an item of `other`), and
+/// [Dict::fill_defaults] only adds entries, so neither has comments to merge.
+///
+/// non-exhaustive because `Concatenate` is there only with the "alloc" feature,
+/// which another crate in the build may turn on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CommentMergePolicy {
+    /// the comment of `self`.
+    KeepOurs,
+    /// the comment of `other`.
+    KeepTheirs,
+    /// both, ours first, with the separator between (so `"\n"` starts theirs on the
+    /// next line). a comment the same on both sides is kept once. the joined text
+    /// is interned into the arena.
+    #[cfg(feature = "alloc")]
+    Concatenate(&'static str),
+}
+impl CommentMergePolicy {
+    /// the comment to keep, given `ours` and `theirs`.
+    #[cfg_attr(not(feature = "alloc"), allow(unused_variables))]
+    pub fn merge<'a>(
+        self,
+        arena: &mut dyn Build<'a>,
+        ours: Option<Comment<'a>>,
+        theirs: Option<Comment<'a>>,
+    ) -> Result<Option<Comment<'a>>, &'static str> {
+        Ok(match (ours, theirs) {
+            (Some(ours), Some(theirs)) => Some(match self {
+                CommentMergePolicy::KeepOurs => ours,
+                CommentMergePolicy::KeepTheirs => theirs,
+                #[cfg(feature = "alloc")]
+                CommentMergePolicy::Concatenate(_) if ours.value == theirs.value => ours,
+                #[cfg(feature = "alloc")]
+                CommentMergePolicy::Concatenate(separator) => {
+                    concatenate(arena, ours, separator, theirs)?
+                }
+            }),
+            (ours, theirs) => ours.or(theirs),
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn concatenate<'a>(
+    arena: &mut dyn Build<'a>,
+    ours: Comment<'a>,
+    separator: &str,
+    theirs: Comment<'a>,
+) -> Result<Comment<'a>, &'static str> {
+    let mut text = ours.value.joined();
+    text.push_str(separator);
+    text.push_str(&theirs.value.joined());
+    Comment::interned(arena, &text)
+}
+
+fn merge<'a>(
+    ours: Entries<'a>,
+    arena: &mut dyn Build<'a>,
+    other: Entries<'a>,
+    depth: MergeDepth,
+    comments: Option<CommentMergePolicy>,
+) -> Result<Entries<'a>, &'static str> {
+    let mut count = 0usize;
+    for cell in ours {
+        let mut entry = cell.get();
+        if let Some(at) = entry.key.find_linearly_in(other) {
+            let theirs = other[at].get();
+            entry.item = match (depth, entry.item, theirs.item) {
+                (
+                    MergeDepth::Deep,
+                    Item::Dict {
+                        prolog,
+                        cells,
+                        epilog,
+                    },
+                    Item::Dict {
+                        prolog: their_prolog,
+                        cells: more,
+                        epilog: their_epilog,
+                    },
+                ) => match comments {
+                    None => Item::Dict {
+                        prolog,
+                        cells: merge(cells, arena, more, depth, comments)?,
+                        epilog,
+                    },
+                    Some(policy) => Item::Dict {
+                        prolog: policy.merge(arena, prolog, their_prolog)?,
+                        cells: merge(cells, arena, more, depth, comments)?,
+                        epilog: policy.merge(arena, epilog, their_epilog)?,
+                    },
+                },
+                (_, old, mut item) => {
+                    if let Some(policy) = comments {
+                        if !matches!(item, Item::Text { .. }) {
+                            item.set_prolog(policy.merge(arena, old.prolog(), item.prolog())?)?;
+                        }
+                        item.set_epilog(policy.merge(arena, old.epilog(), item.epilog())?);
+                    }
+                    item
+                }
+            };
+            if let Some(policy) = comments {
+                entry.before = policy.merge(arena, entry.before, theirs.before)?;
+            }
+        }
+        arena.push_entry(entry)?;
+        count += 1;
+    }
+    for (at, cell) in other.iter().enumerate() {
+        let entry = cell.get();
+        if entry.key.find_linearly_in(other) == Some(at)
+            && entry.key.find_linearly_in(ours).is_none()
+        {
+            arena.push_entry(entry)?;
+            count += 1;
+        }
+    }
+    finish_entries(arena, count)
+}
+
+/// the iterator from [Dict::alias_chain]. it stops after the first key that is not
+/// an alias (or is missing). if the aliases go round in a circle, it stops after one
+/// more key than the dict has entries (so some key is repeated).
+pub struct AliasChain<'a> {
+    cells: Entries<'a>,
+    next: Option<Value<'a>>,
+    left: usize,
+}
+impl<'a> Iterator for AliasChain<'a> {
+    type Item = Value<'a>;
+    fn next(&mut self) -> Option<Value<'a>> {
+        let key = self.next.take()?;
+        if self.left == 0 {
+            return None;
+        }
+        self.left -= 1;
+        self.next = key
+            .find_linearly_in(self.cells)
+            .and_then(|at| self.cells[at].get().item.alias_of())
+            .map(Value::from);
+        Some(key)
+    }
+}
+
+/// a new slice of entries (without gaps or comments) from `(key, item)` pairs, like
+/// [FromIterator], but into the `arena` (which `pairs` must not use while it runs).
+/// fails if a key is repeated.
+pub fn collect_entries<'a>(
+    arena: &mut dyn Build<'a>,
+    pairs: impl IntoIterator<Item = (Value<'a>, Item<'a>)>,
+) -> Result<Entries<'a>, &'static str> {
+    let mut count = 0usize;
+    for (key, item) in pairs {
+        arena.push_entry(Entry {
+            key,
+            item,
+            ..Entry::default()
+        })?;
+        count += 1;
+    }
+    let cells = finish_entries(arena, count)?;
+    for (at, cell) in cells.iter().enumerate() {
+        if cell.get().key.find_linearly_in(&cells[..at]).is_some() {
+            return Err("duplicate key");
+        }
+    }
+    Ok(cells)
+}
+
+/// a new slice from `items`, like [FromIterator], but into the `arena` (which `items`
+/// must not use while it runs).
+pub fn collect_items<'a>(
+    arena: &mut dyn Build<'a>,
+    items: impl IntoIterator<Item = Item<'a>>,
+) -> Result<Items<'a>, &'static str> {
+    let mut count = 0usize;
+    for item in items {
+        arena.push_item(item)?;
+        count += 1;
+    }
+    finish_items(arena, count)
+}
+
+/// shift the cells between `from` and `to`, so the one at `from` ends up at `to`.
+fn move_cell<T>(cells: &[Cell<T>], from: usize, to: usize) {
+    if from < to {
+        for at in from..to {
+            cells[at].swap(&cells[at + 1]);
+        }
+    } else {
+        for at in (to..from).rev() {
+            cells[at].swap(&cells[at + 1]);
+        }
+    }
+}
+
+/// `true` if `fill_defaults` would add anything.
+fn lacks(cells: Entries<'_>, defaults: Entries<'_>) -> bool {
+    defaults.iter().any(|cell| {
+        let default = cell.get();
+        match default.key.find_linearly_in(cells) {
+            None => true,
+            Some(at) => match (cells[at].get().item, default.item) {
+                (
+                    Item::Dict { cells, .. },
+                    Item::Dict {
+                        cells: template, ..
+                    },
+                ) => lacks(cells, template),
+                _ => false,
+            },
+        }
+    })
+}
+
+fn finish_items<'a>(arena: &mut dyn Build<'a>, count: usize) -> Result<Items<'a>, &'static str> {
+    if count == 0 {
+        Ok(&[])
+    } else {
+        arena.finish_items(count)
+    }
+}
+fn finish_entries<'a>(
+    arena: &mut dyn Build<'a>,
+    count: usize,
+) -> Result<Entries<'a>, &'static str> {
+    if count == 0 {
+        Ok(&[])
+    } else {
+        arena.finish_entries(count)
+    }
+}
+
+/// `true` if any of the `paths` goes through (or ends at) `here`.
+fn wanted<const ENTRY: bool>(here: &Trail<'_, '_>, paths: &[Path<'_, ENTRY>]) -> bool {
+    let depth = here.depth();
+    paths.iter().any(|path| {
+        let branches = path.branches();
+        branches.len() > depth && Trail::follows(Some(here), &branches[..depth])
+    })
+}
+
+/// a copy of `item` with only the parts that `paths` lead to.
+fn prune<'a, const ENTRY: bool>(
+    arena: &mut dyn Build<'a>,
+    item: Item<'a>,
+    trail: Option<&Trail<'_, 'a>>,
+    paths: &[Path<'_, ENTRY>],
+) -> Result<Item<'a>, &'static str> {
+    let depth = trail.map_or(0, Trail::depth);
+    let ends_here = paths.iter().any(|path| {
+        let branches = path.branches();
+        branches.len() == depth + 1 && Trail::follows(trail, &branches[..depth])
+    });
+    if ends_here {
+        return Ok(item);
+    }
+    match item {
+        Item::Text { .. } => Ok(item),
+        Item::List {
+            prolog,
+            cells,
+            epilog,
+        } => {
+            let mut count = 0usize;
+            for (at, cell) in cells.iter().enumerate() {
+                let here = Trail {
+                    up: trail,
+                    branch: Branch::Item(at),
+                };
+                if wanted(&here, paths) {
+                    let kept = prune(arena, cell.get(), Some(&here), paths)?;
+                    arena.push_item(kept)?;
+                    count += 1;
+                }
+            }
+            Ok(Item::List {
+                prolog,
+                cells: finish_items(arena, count)?,
+                epilog,
+            })
+        }
+        Item::Dict {
+            prolog,
+            cells,
+            epilog,
+        } => {
+            let mut count = 0usize;
+            for (at, cell) in cells.iter().enumerate() {
+                let mut entry = cell.get();
+                if entry.key.find_linearly_in(cells) != Some(at) {
+                    continue; // a walk would never reach a duplicate key
+                }
+                let here = Trail {
+                    up: trail,
+                    branch: Branch::Entry(entry.key),
+                };
+                if wanted(&here, paths) {
+                    entry.item = prune(arena, entry.item, Some(&here), paths)?;
+                    arena.push_entry(entry)?;
+                    count += 1;
+                }
+            }
+            Ok(Item::Dict {
+                prolog,
+                cells: finish_entries(arena, count)?,
+                epilog,
+            })
+        }
+    }
+}
@@ -0,0 +1,499 @@
+//! visiting every comment, e.g. to extract them for translation and put them back.
+//!
+//! the location of a comment is a [Trail] (None for the [File] itself) and a [Slot].
+
+use crate::walk::{Branch, Trail};
+use crate::{Comment, Entries, File, Item, Items};
+
+/// which of the comments at a location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Slot {
+    /// [File::hashbang]
+    Hashbang,
+    /// the introduction of a [File], [Item::List] or [Item::Dict]
+    Prolog,
+    /// [Entry::before](crate::Entry::before)
+    Before,
+    /// the comment after an [Item]
+    Epilog,
+}
+
+/// the callback for [visit], returning Some replaces the comment.
+pub type Visitor<'v, 'a> =
+    dyn FnMut(Option<&Trail<'_, 'a>>, Slot, Comment<'a>) -> Option<Comment<'a>> + 'v;
+
+/// call `visitor` for every comment, in the order they would be encoded.
+///
+/// replacements happen in place (via the cells). note that entries with duplicate
+/// keys also have duplicate locations.
+pub fn visit<'a>(file: &mut File<'a>, visitor: &mut Visitor<'_, 'a>) {
+    edit(file, &mut |trail, slot, comment| {
+        visitor(trail, slot, comment).map(Some)
+    });
+}
+
+impl<'a> File<'a> {
+    /// take out every comment for which `predicate` is true (e.g. anything marked
+    /// INTERNAL, before publishing), in place. returns how many were taken out.
+    pub fn remove_comments_where(
+        &mut self,
+        predicate: &mut dyn FnMut(Option<&Trail<'_, 'a>>, Slot, Comment<'a>) -> bool,
+    ) -> usize {
+        let mut count = 0;
+        edit(self, &mut |trail, slot, comment| {
+            if !predicate(trail, slot, comment) {
+                return None;
+            }
+            count += 1;
+            Some(None)
+        });
+        count
+    }
+}
+
+/// like [Visitor], but returning Some(None) takes the comment out.
+type Editor<'v, 'a> =
+    dyn FnMut(Option<&Trail<'_, 'a>>, Slot, Comment<'a>) -> Option<Option<Comment<'a>>> + 'v;
+
+fn edit<'a>(file: &mut File<'a>, visitor: &mut Editor<'_, 'a>) {
+    swap(&mut file.hashbang, None, Slot::Hashbang, visitor);
+    swap(&mut file.prolog, None, Slot::Prolog, visitor);
+    entries(file.cells, None, visitor);
+}
+
+fn swap<'a>(
+    option: &mut Option<Comment<'a>>,
+    trail: Option<&Trail<'_, 'a>>,
+    slot: Slot,
+    visitor: &mut Editor<'_, 'a>,
+) -> bool {
+    let Some(comment) = *option else {
+        return false;
+    };
+    let Some(replacement) = visitor(trail, slot, comment) else {
+        return false;
+    };
+    *option = replacement;
+    true
+}
+
+fn item<'a>(item: &mut Item<'a>, here: &Trail<'_, 'a>, visitor: &mut Editor<'_, 'a>) -> bool {
+    match item {
+        Item::Text { epilog, .. } => swap(epilog, Some(here), Slot::Epilog, visitor),
+        Item::List {
+            prolog,
+            cells,
+            epilog,
+        } => {
+            let changed = swap(prolog, Some(here), Slot::Prolog, visitor);
+            items(cells, Some(here), visitor);
+            swap(epilog, Some(here), Slot::Epilog, visitor) || changed
+        }
+        Item::Dict {
+            prolog,
+            cells,
+            epilog,
+        } => {
+            let changed = swap(prolog, Some(here), Slot::Prolog, visitor);
+            entries(cells, Some(here), visitor);
+            swap(epilog, Some(here), Slot::Epilog, visitor) || changed
+        }
+    }
+}
+
+fn items<'a>(cells: Items<'a>, trail: Option<&Trail<'_, 'a>>, visitor: &mut Editor<'_, 'a>) {
+    for (at, cell) in cells.iter().enumerate() {
+        let here = Trail {
+            up: trail,
+            branch: Branch::Item(at),
+        };
+        let mut value = cell.get();
+        if item(&mut value, &here, visitor) {
+            cell.set(value);
+        }
+    }
+}
+
+fn entries<'a>(cells: Entries<'a>, trail: Option<&Trail<'_, 'a>>, visitor: &mut Editor<'_, 'a>) {
+    for cell in cells {
+        let mut entry = cell.get();
+        let here = Trail {
+            up: trail,
+            branch: Branch::Entry(entry.key),
+        };
+        let before = swap(&mut entry.before, Some(&here), Slot::Before, visitor);
+        if item(&mut entry.item, &here, visitor) || before {
+            cell.set(entry);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod catalog {
+    extern crate alloc;
+
+    use super::{Slot, visit};
+    use crate::walk::{Branch, Trail};
+    use crate::{Comment, File, Item};
+    use alloc::collections::BTreeMap;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::fmt::{Display, Formatter};
+
+    /// a comment and where it is, see [File::comments].
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Located<'a> {
+        /// the steps from the root to the item (none for the [File] itself).
+        pub branches: Vec<Branch<'a>>,
+        /// which of the comments there.
+        pub slot: Slot,
+        /// the comment itself.
+        pub comment: Comment<'a>,
+    }
+
+    impl<'a> File<'a> {
+        /// every comment with its location, in the order they would be encoded (see
+        /// [visit]).
+        pub fn comments(&self) -> impl Iterator<Item = Located<'a>> {
+            let mut found = Vec::new();
+            let mut copy = *self;
+            visit(&mut copy, &mut |trail, slot, comment| {
+                let mut branches = Vec::new();
+                let mut up = trail;
+                while let Some(here) = up {
+                    branches.push(here.branch);
+                    up = here.up;
+                }
+                branches.reverse();
+                found.push(Located {
+                    branches,
+                    slot,
+                    comment,
+                });
+                None
+            });
+            found.into_iter()
+        }
+    }
+
+    impl<'a> File<'a> {
+        /// the comments (see [File::comments]) that contain `needle`. a comment on
+        /// more than one line is searched as one text, with `\n` between the lines.
+        pub fn find_comments<'n>(&self, needle: &'n str) -> impl Iterator<Item = Located<'a>> + 'n
+        where
+            'a: 'n,
+        {
+            self.comments()
+                .filter(move |located| located.comment.value.joined().contains(needle))
+        }
+        /// like [File::find_comments], for the comments that `regex` matches.
+        #[cfg(feature = "regex")]
+        pub fn find_comments_matching<'r>(
+            &self,
+            regex: &'r regex::Regex,
+        ) -> impl Iterator<Item = Located<'a>> + 'r
+        where
+            'a: 'r,
+        {
+            self.comments()
+                .filter(move |located| regex.is_match(&located.comment.value.joined()))
+        }
+    }
+
+    /// a line of a comment that starts a task, see [File::task_comments].
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Task<'a> {
+        /// the marker that was found, e.g. `TODO`.
+        pub marker: &'a str,
+        /// the rest of the line after the marker (and any `:` and spaces).
+        pub text: &'a str,
+        /// the steps from the root to the item (none for the [File] itself).
+        pub branches: Vec<Branch<'a>>,
+        /// which of the comments there.
+        pub slot: Slot,
+    }
+
+    impl<'a> File<'a> {
+        /// every line of every comment (see [File::comments]) with one of `markers`
+        /// (e.g. `["TODO", "FIXME", "XXX"]`) as a word of its own. the first marker
+        /// on a line counts.
+        pub fn task_comments(&self, markers: &[&str]) -> impl Iterator<Item = Task<'a>> {
+            let mut tasks = Vec::new();
+            for located in self.comments() {
+                for line in located.comment.value.lines() {
+                    let Some((marker, text)) = find_marker(line, markers) else {
+                        continue;
+                    };
+                    tasks.push(Task {
+                        marker,
+                        text,
+                        branches: located.branches.clone(),
+                        slot: located.slot,
+                    });
+                }
+            }
+            tasks.into_iter()
+        }
+    }
+
+    fn find_marker<'a>(line: &'a str, markers: &[&str]) -> Option<(&'a str, &'a str)> {
+        let word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let mut found: Option<(usize, usize)> = None;
+        for marker in markers.iter().filter(|marker| !marker.is_empty()) {
+            for (at, _) in line.match_indices(marker) {
+                let end = at + marker.len();
+                if word(line[..at].chars().next_back()) || word(line[end..].chars().next()) {
+                    continue;
+                }
+                if found.map_or(true, |(first, _)| at < first) {
+                    found = Some((at, end));
+                }
+                break;
+            }
+        }
+        let (at, end) = found?;
+        let text = line[end..].trim_start_matches(':').trim();
+        Some((&line[at..end], text))
+    }
+
+    /// what [File::coverage] found.
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub struct Coverage<'a> {
+        /// how many entries were looked at.
+        pub entries: usize,
+        /// the entries without a `before` comment.
+        pub undocumented: Vec<Vec<Branch<'a>>>,
+        /// how many dicts (inside the file) were looked at.
+        pub dicts: usize,
+        /// the dicts without a prolog.
+        pub without_prolog: Vec<Vec<Branch<'a>>>,
+    }
+    impl<'a> Coverage<'a> {
+        /// true if every entry and dict that was looked at has its comment.
+        pub fn is_complete(&self) -> bool {
+            self.undocumented.is_empty() && self.without_prolog.is_empty()
+        }
+        /// `levels` is how many dicts deep `cells` are (1 for the file itself).
+        fn entries(
+            &mut self,
+            cells: crate::Entries<'a>,
+            trail: &mut Vec<Branch<'a>>,
+            levels: usize,
+            depth: usize,
+        ) {
+            for cell in cells {
+                let entry = cell.get();
+                trail.push(Branch::Entry(entry.key));
+                self.entries += 1;
+                if entry.before.is_none() {
+                    self.undocumented.push(trail.clone());
+                }
+                self.item(entry.item, trail, levels, depth);
+                trail.pop();
+            }
+        }
+        fn item(
+            &mut self,
+            item: Item<'a>,
+            trail: &mut Vec<Branch<'a>>,
+            levels: usize,
+            depth: usize,
+        ) {
+            match item {
+                Item::Text { .. } => {}
+                Item::List { cells, .. } => {
+                    for (at, cell) in cells.iter().enumerate() {
+                        trail.push(Branch::Item(at));
+                        self.item(cell.get(), trail, levels, depth);
+                        trail.pop();
+                    }
+                }
+                Item::Dict { prolog, cells, .. } if levels < depth => {
+                    self.dicts += 1;
+                    if prolog.is_none() {
+                        self.without_prolog.push(trail.clone());
+                    }
+                    self.entries(cells, trail, levels + 1, depth);
+                }
+                Item::Dict { .. } => {}
+            }
+        }
+    }
+
+    impl<'a> File<'a> {
+        /// which entries lack a `before` comment, and which dicts lack a prolog, down
+        /// to `depth` dicts deep (1 for only the outermost entries, [usize::MAX] for
+        /// all). lists are not a level of their own, and a dict is looked at (its
+        /// prolog and its entries) only if it is within the `depth`. a subtree can be
+        /// picked out by the start of the branches.
+        pub fn coverage(&self, depth: usize) -> Coverage<'a> {
+            let mut coverage = Coverage::default();
+            if depth > 0 {
+                coverage.entries(self.cells, &mut Vec::new(), 1, depth);
+            }
+            coverage
+        }
+    }
+
+    /// where a comment is, as the key of a catalog (see [extract]). it is made of the
+    /// steps themselves, so two locations never share a key (whatever is in the
+    /// keys of the entries). it displays as e.g. `{server}[0] before`, but that text
+    /// is only for people: a key with braces in it can display like another path.
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct CatalogKey {
+        /// the steps from the root to the item (none for the [File] itself).
+        pub steps: Vec<CatalogStep>,
+        /// which of the comments there.
+        pub slot: Slot,
+    }
+    /// one step of a [CatalogKey], a [Branch::Entry] or [Branch::Item] that owns its key.
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub enum CatalogStep {
+        /// the key of an entry in a dict
+        Entry(String),
+        /// the index of an item in a list
+        Item(usize),
+    }
+    impl Display for CatalogKey {
+        fn fmt(&self, out: &mut Formatter<'_>) -> core::fmt::Result {
+            for step in &self.steps {
+                match step {
+                    CatalogStep::Entry(key) => write!(out, "{{{key}}}")?,
+                    CatalogStep::Item(at) => write!(out, "[{at}]")?,
+                }
+            }
+            if !self.steps.is_empty() {
+                out.write_str(" ")?;
+            }
+            Display::fmt(&self.slot, out)
+        }
+    }
+
+    /// the catalog key for a comment location.
+    pub fn key(trail: Option<&Trail<'_, '_>>, slot: Slot) -> CatalogKey {
+        let mut steps = Vec::new();
+        let mut up = trail;
+        while let Some(trail) = up {
+            steps.push(match trail.branch {
+                Branch::Item(at) => CatalogStep::Item(at),
+                Branch::Entry(key) => CatalogStep::Entry(key.to_string()),
+                _ => unreachable!("a trail has only Item and Entry steps"),
+            });
+            up = trail.up;
+        }
+        steps.reverse();
+        CatalogKey { steps, slot }
+    }
+
+    /// copy every comment into a catalog, keyed by location (see [key]).
+    pub fn extract(file: &File<'_>) -> BTreeMap<CatalogKey, String> {
+        let mut catalog = BTreeMap::new();
+        let mut copy = *file;
+        visit(&mut copy, &mut |trail, slot, comment| {
+            catalog.insert(key(trail, slot), comment.value.to_string());
+            None
+        });
+        catalog
+    }
+
+    /// how a comment differs, see [diff_comments].
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum CommentChange {
+        /// only in the new file
+        Added(String),
+        /// only in the old file
+        Removed(String),
+        /// in both, old then new
+        Changed(String, String),
+    }
+
+    /// the comments that differ between `old` and `new`, keyed by location (see
+    /// [key]), whatever happened to the values. a comment moves with its entry's
+    /// key, so a renamed key shows as a removed and an added comment.
+    pub fn diff_comments(old: &File<'_>, new: &File<'_>) -> BTreeMap<CatalogKey, CommentChange> {
+        let mut new = extract(new);
+        let mut changes = BTreeMap::new();
+        for (key, before) in extract(old) {
+            match new.remove(&key) {
+                None => {
+                    changes.insert(key, CommentChange::Removed(before));
+                }
+                Some(after) if after != before => {
+                    changes.insert(key, CommentChange::Changed(before, after));
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, after) in new {
+            changes.insert(key, CommentChange::Added(after));
+        }
+        changes
+    }
+
+    /// swap in the comments from a (translated) catalog. returns how many were found.
+    ///
+    /// comments missing from the catalog are left as is, and comments are never added.
+    pub fn inject<'a>(file: &mut File<'a>, catalog: &'a BTreeMap<CatalogKey, String>) -> usize {
+        let mut count = 0;
+        visit(file, &mut |trail, slot, _| {
+            let translated = catalog.get(&key(trail, slot))?;
+            count += 1;
+            Comment::some(translated)
+        });
+        count
+    }
+}
+#[cfg(feature = "alloc")]
+pub use catalog::{
+    CatalogKey, CatalogStep, CommentChange, Coverage, Located, Task, diff_comments, extract,
+    inject, key,
+};
+
+#[cfg(feature = "gfm")]
+mod gfm {
+    extern crate alloc;
+
+    use crate::Comment;
+    use alloc::string::{String, ToString};
+    use markdown::mdast::Node;
+
+    impl<'a> Comment<'a> {
+        /// the content (GitHub Flavored Markdown) rendered as HTML.
+        pub fn to_html(&self) -> String {
+            let options = markdown::Options::gfm();
+            markdown::to_html_with_options(&self.value.joined(), &options)
+                .expect("only MDX can fail, and that is not enabled")
+        }
+        /// the content without the Markdown: just the words (and code), with a
+        /// line for each paragraph, heading, list item, code block or table row.
+        pub fn to_plain_text(&self) -> String {
+            let options = markdown::ParseOptions::gfm();
+            let root = markdown::to_mdast(&self.value.joined(), &options)
+                .expect("only MDX can fail, and that is not enabled");
+            let mut lines = String::new();
+            plain(&root, &mut lines);
+            lines
+        }
+    }
+
+    fn plain(node: &Node, lines: &mut String) {
+        match node {
+            Node::Paragraph(_)
+            | Node::Heading(_)
+            | Node::Code(_)
+            | Node::Math(_)
+            | Node::Html(_)
+            | Node::TableRow(_) => {
+                if !lines.is_empty() {
+                    lines.push('\n');
+                }
+                lines.push_str(&node.to_string());
+            }
+            _ => {
+                for child in node.children().into_iter().flatten() {
+                    plain(child, lines);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! shared test cases that every implementation of the format should agree on.
+//!
+//! a case is three pieces of text:
+//!  + the input (tindalwic encoded),
+//!  + the expected tree (see [Tree] for the notation),
+//!  + the expected re-encoding (usually identical to the input).
+//!
+//! the cases live as plain files (`NAME.tindalwic`, `NAME.tree` and optionally
+//! `NAME.encoded`) in the `tests/conformance` directory, so that implementations in
+//! other languages can run them without any Rust involved.
+
+use crate::{Comment, Entry, File, Item, Value};
+use core::fmt::{Display, Formatter, Result, Write};
+
+/// the non-normative grammar sketch, as data.
+pub const GRAMMAR: &str = include_str!("../tindalwic.abnf");
+
+/// a language-neutral dump of a parsed [File], one node per line.
+///
+/// nesting is shown by TAB indentation. strings use JSON quoting. the nodes are:
+///  + `hashbang "..."` / `prolog "..."` / `epilog "..."` / `before "..."`
+///  + `gap`
+///  + `entry "key"` followed by its gap, before and item (indented)
+///  + `text "value"` followed by its epilog (indented)
+///  + `list` / `dict` followed by prolog, contents and epilog (indented)
+pub struct Tree<'a>(pub File<'a>);
+impl<'a> Display for Tree<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        let Tree(file) = self;
+        let mut dump = Dump { out, depth: 0 };
+        dump.comment("hashbang", &file.hashbang)?;
+        dump.comment("prolog", &file.prolog)?;
+        for cell in file.cells {
+            dump.entry(&cell.get())?;
+        }
+        Ok(())
+    }
+}
+
+struct Dump<'o, 'f> {
+    out: &'o mut Formatter<'f>,
+    depth: usize,
+}
+impl<'o, 'f> Dump<'o, 'f> {
+    fn node(&mut self, name: &str) -> Result {
+        for _ in 0..self.depth {
+            self.out.write_char('\t')?;
+        }
+        self.out.write_str(name)
+    }
+    fn quoted(&mut self, value: &Value<'_>) -> Result {
+        self.out.write_str(" \"")?;
+        for (i, line) in value.lines().enumerate() {
+            if i != 0 {
+                self.out.write_str("\\n")?;
+            }
+            for c in line.chars() {
+                match c {
+                    '"' => self.out.write_str("\\\"")?,
+                    '\\' => self.out.write_str("\\\\")?,
+                    '\t' => self.out.write_str("\\t")?,
+                    '\r' => self.out.write_str("\\r")?,
+                    c if (c as u32) < 0x20 => write!(self.out, "\\u{:04x}", c as u32)?,
+                    c => self.out.write_char(c)?,
+                }
+            }
+        }
+        self.out.write_str("\"\n")
+    }
+    fn comment(&mut self, name: &str, comment: &Option<Comment<'_>>) -> Result {
+        if let Some(comment) = comment {
+            self.node(name)?;
+            self.quoted(&comment.value)?;
+        }
+        Ok(())
+    }
+    fn entry(&mut self, entry: &Entry<'_>) -> Result {
+        self.node("entry")?;
+        self.quoted(&entry.key)?;
+        self.depth += 1;
+        if entry.gap {
+            self.node("gap\n")?;
+        }
+        self.comment("before", &entry.before)?;
+        self.item(&entry.item)?;
+        self.depth -= 1;
+        Ok(())
+    }
+    fn item(&mut self, item: &Item<'_>) -> Result {
+        match item {
+            Item::Text { value, epilog } => {
+                self.node("text")?;
+                self.quoted(value)?;
+                self.depth += 1;
+                self.comment("epilog", epilog)?;
+            }
+            Item::List {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                self.node("list\n")?;
+                self.depth += 1;
+                self.comment("prolog", prolog)?;
+                for cell in *cells {
+                    self.item(&cell.get())?;
+                }
+                self.comment("epilog", epilog)?;
+            }
+            Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                self.node("dict\n")?;
+                self.depth += 1;
+                self.comment("prolog", prolog)?;
+                for cell in *cells {
+                    self.entry(&cell.get())?;
+                }
+                self.comment("epilog", epilog)?;
+            }
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+/// one conformance test case, usually read from the `tests/conformance` files.
+#[derive(Clone, Copy, Debug)]
+pub struct Case<'a> {
+    /// the tindalwic encoded data
+    pub input: &'a str,
+    /// the expected [Tree] (trailing newlines are not significant)
+    pub tree: &'a str,
+    /// the expected re-encoding, None means same as `input`
+    pub encoded: Option<&'a str>,
+}
+
+#[cfg(feature = "alloc")]
+mod run {
+    extern crate alloc;
+
+    use super::{Case, Tree};
+    use crate::parse::{Parse, ParseError};
+    use alloc::string::{String, ToString};
+    use core::fmt::{Display, Formatter, Result};
+
+    /// how a conformance [Case] failed.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum Failure {
+        /// the input could not be parsed
+        Parse(ParseError),
+        /// the parsed tree differs, payload is the actual [Tree]
+        Tree(String),
+        /// the re-encoding differs, payload is the actual encoding
+        Encoded(String),
+    }
+    impl core::error::Error for Failure {}
+    impl Display for Failure {
+        fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+            match self {
+                Failure::Parse(error) => write!(out, "parse {error}"),
+                Failure::Tree(actual) => write!(out, "tree differs, actual:\n{actual}"),
+                Failure::Encoded(actual) => write!(out, "encoding differs, actual:\n{actual}"),
+            }
+        }
+    }
+
+    impl<'a> Case<'a> {
+        /// parse the input, compare the tree, then compare the re-encoding.
+        pub fn run(&self, parser: &mut dyn Parse<'a>) -> core::result::Result<(), Failure> {
+            let file = parser.first_error(self.input).map_err(Failure::Parse)?;
+            let tree = Tree(file).to_string();
+            if tree.trim_end_matches('\n') != self.tree.trim_end_matches('\n') {
+                return Err(Failure::Tree(tree));
+            }
+            let encoded = file.to_string();
+            if encoded != self.encoded.unwrap_or(self.input) {
+                return Err(Failure::Encoded(encoded));
+            }
+            Ok(())
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+pub use run::Failure;
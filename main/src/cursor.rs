@@ -0,0 +1,150 @@
+//! a position in the tree that moves one step at a time, for interactive editors.
+//!
+//! all this stuff is enabled by the "alloc" feature (the way back up is a stack).
+//!
+//! moving never starts again from the root: each step looks only at the cells of the
+//! focus or of its parent. the parents are remembered as their cells, so changes
+//! made at the focus are seen on the way back up.
+
+extern crate alloc;
+
+use crate::walk::Branch;
+use crate::{Entries, Entry, File, Item, Items, Value};
+use alloc::vec::Vec;
+use core::cell::Cell;
+
+#[derive(Clone, Copy, Debug)]
+enum Frame<'a> {
+    Entry(Entries<'a>, usize),
+    Item(Items<'a>, usize),
+}
+impl<'a> Frame<'a> {
+    fn item(&self) -> Item<'a> {
+        match *self {
+            Frame::Entry(cells, at) => cells[at].get().item,
+            Frame::Item(cells, at) => cells[at].get(),
+        }
+    }
+}
+
+/// the focus is an entry or list item, or the outermost entries of a [File] (where
+/// it starts, and where there is nothing above).
+#[derive(Clone, Debug)]
+pub struct Cursor<'a> {
+    root: Entries<'a>,
+    stack: Vec<Frame<'a>>,
+}
+impl<'a> Cursor<'a> {
+    /// a cursor at the outermost entries of `file`.
+    pub fn new(file: &File<'a>) -> Self {
+        Cursor {
+            root: file.cells,
+            stack: Vec::new(),
+        }
+    }
+    /// the number of steps down from the root.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+    /// the steps from the root to the focus (the same as a [walk::Trail](crate::walk::Trail)).
+    pub fn branches(&self) -> impl Iterator<Item = Branch<'a>> + '_ {
+        self.stack.iter().map(|frame| match *frame {
+            Frame::Entry(cells, at) => Branch::Entry(cells[at].get().key),
+            Frame::Item(_, at) => Branch::Item(at),
+        })
+    }
+    /// the item at the focus, None at the root.
+    pub fn item(&self) -> Option<Item<'a>> {
+        Some(self.stack.last()?.item())
+    }
+    /// the cell of the entry at the focus, None unless the focus is in a dict.
+    pub fn entry(&self) -> Option<&'a Cell<Entry<'a>>> {
+        match *self.stack.last()? {
+            Frame::Entry(cells, at) => Some(&cells[at]),
+            Frame::Item(..) => None,
+        }
+    }
+    /// the key of the entry at the focus, None unless the focus is in a dict.
+    pub fn key(&self) -> Option<Value<'a>> {
+        Some(self.entry()?.get().key)
+    }
+    /// replace the item at the focus (in place, via its cell). fails at the root.
+    pub fn set_item(&self, item: Item<'a>) -> Result<(), &'static str> {
+        match self.stack.last() {
+            None => Err("cursor is at the root"),
+            Some(Frame::Entry(cells, at)) => {
+                let mut entry = cells[*at].get();
+                entry.item = item;
+                cells[*at].set(entry);
+                Ok(())
+            }
+            Some(Frame::Item(cells, at)) => {
+                cells[*at].set(item);
+                Ok(())
+            }
+        }
+    }
+    fn entries(&self) -> Option<Entries<'a>> {
+        match self.stack.last() {
+            None => Some(self.root),
+            Some(frame) => frame.item().as_dict(),
+        }
+    }
+    /// move to the entry with `key`, in the dict at the focus. false (and the focus
+    /// stays) if there is no such entry.
+    pub fn down_key(&mut self, key: &str) -> bool {
+        let Some(cells) = self.entries() else {
+            return false;
+        };
+        let Some(at) = Value::from(key).find_linearly_in(cells) else {
+            return false;
+        };
+        self.stack.push(Frame::Entry(cells, at));
+        true
+    }
+    /// move to the item at `index`, in the list at the focus (or the entry at
+    /// `index`, in a dict). false (and the focus stays) if there is no such index.
+    pub fn down_index(&mut self, index: usize) -> bool {
+        let (frame, len) = match self.stack.last().map(Frame::item) {
+            None => (Frame::Entry(self.root, index), self.root.len()),
+            Some(Item::List { cells, .. }) => (Frame::Item(cells, index), cells.len()),
+            Some(Item::Dict { cells, .. }) => (Frame::Entry(cells, index), cells.len()),
+            Some(Item::Text { .. }) => return false,
+        };
+        if index >= len {
+            return false;
+        }
+        self.stack.push(frame);
+        true
+    }
+    /// move to the list or dict that holds the focus. false at the root.
+    pub fn up(&mut self) -> bool {
+        self.stack.pop().is_some()
+    }
+    /// move `by` places along (negative for back) within the same list or dict.
+    /// false (and the focus stays) if that goes past either end, or at the root.
+    pub fn sibling(&mut self, by: isize) -> bool {
+        let Some(frame) = self.stack.last_mut() else {
+            return false;
+        };
+        let (len, at) = match frame {
+            Frame::Entry(cells, at) => (cells.len(), at),
+            Frame::Item(cells, at) => (cells.len(), at),
+        };
+        match at.checked_add_signed(by) {
+            Some(next) if next < len => {
+                *at = next;
+                true
+            }
+            _ => false,
+        }
+    }
+    /// [Cursor::sibling] by one.
+    pub fn next_sibling(&mut self) -> bool {
+        self.sibling(1)
+    }
+    /// [Cursor::sibling] by minus one.
+    pub fn prev_sibling(&mut self) -> bool {
+        self.sibling(-1)
+    }
+}
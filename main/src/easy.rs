@@ -0,0 +1,168 @@
+//! the simple read-modify-write case, with no lifetimes and no matching on [Item].
+//!
+//! all this stuff is enabled by the "std" and "bumpalo" features together.
+//!
+//! a [Document] owns its text and parses it again for each call, into an arena that
+//! is dropped before returning, so nothing borrows. that suits a few reads and
+//! writes of a small file. for more, parse once with an Arena and use the tree.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! let mut config = tindalwic::easy::load("app.tindalwic")?;
+//! let port = config.get_str(&["server", "port"]).unwrap_or("8080".into());
+//! config.set_str(&["server", "port"], &port)?;
+//! config.save("app.tindalwic")?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+extern crate alloc;
+extern crate std;
+
+use crate::bumpalo::Arena;
+use crate::parse::{Build, Parse};
+use crate::{Entries, Entry, Item, Value};
+use alloc::string::{String, ToString};
+use bumpalo::Bump;
+use std::io;
+use std::path::Path;
+
+/// the text of a document, known to parse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Document {
+    text: String,
+}
+
+/// read and check the document at `path`. a syntax error is [io::ErrorKind::InvalidData],
+/// described in GCC format.
+pub fn load(path: impl AsRef<Path>) -> io::Result<Document> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    Document::parse(&path.to_string_lossy(), text)
+}
+
+impl Document {
+    /// check `text`, with `name` standing for where it came from in any errors.
+    pub fn parse(name: &str, text: String) -> io::Result<Document> {
+        let bump = Bump::new();
+        Arena::new(&bump)
+            .format_errors(name, &text, 10)
+            .map_err(|errors| io::Error::new(io::ErrorKind::InvalidData, errors))?;
+        Ok(Document { text })
+    }
+    /// the text, as it would be saved.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+    /// the text at the end of `keys`, each one the key of an entry in the dict found
+    /// by the ones before it. None if a key is missing or something is not a dict
+    /// (or at the end, not a text).
+    pub fn get_str(&self, keys: &[&str]) -> Option<String> {
+        let bump = Bump::new();
+        let file = Arena::new(&bump).first_error(&self.text).ok()?;
+        let mut cells = file.cells;
+        let (last, keys) = keys.split_last()?;
+        for key in keys {
+            let at = Value::from(*key).find_linearly_in(cells)?;
+            let Item::Dict { cells: inner, .. } = cells[at].get().item else {
+                return None;
+            };
+            cells = inner;
+        }
+        let at = Value::from(*last).find_linearly_in(cells)?;
+        match cells[at].get().item {
+            Item::Text { value, .. } => Some(value.joined()),
+            _ => None,
+        }
+    }
+    /// set the text at the end of `keys` (see [Document::get_str]) to `value`, adding
+    /// any entries that are missing, with dicts for all but the last. a text that is
+    /// replaced keeps its comments. no `keys` is an [Error::Usage](crate::Error::Usage),
+    /// and a key whose item is of the wrong type an [Error::Path](crate::Error::Path).
+    pub fn set_str(&mut self, keys: &[&str], value: &str) -> Result<(), crate::Error> {
+        if keys.is_empty() {
+            return Err(crate::Error::Usage("no keys"));
+        }
+        let bump = Bump::new();
+        let mut arena = Arena::new(&bump);
+        let mut file = arena.first_error(&self.text)?;
+        let arena = arena.builder();
+        let keys = keys
+            .iter()
+            .map(|key| arena.intern(key))
+            .collect::<Result<alloc::vec::Vec<_>, _>>()
+            .map_err(crate::Error::Build)?;
+        let value = arena.intern(value).map_err(crate::Error::Build)?;
+        file.cells = set(arena, file.cells, &keys, 0, value)?;
+        self.text = file.to_string();
+        Ok(())
+    }
+    /// write the text to `path`, via [File::save_atomic](crate::File::save_atomic).
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let bump = Bump::new();
+        let file = Arena::new(&bump).first_error(&self.text);
+        file.expect("a Document always parses").save_atomic(path)
+    }
+}
+
+impl<'a> Item<'a> {
+    /// an independent [Document] made from an Item::Dict (its prolog too, but not
+    /// its epilog), that can outlive the buffer this was parsed from. None for a
+    /// text or list, which cannot be a whole document. to keep working with a tree
+    /// instead, see [Item::clone_owned].
+    pub fn detach(&self) -> Option<Document> {
+        let file = crate::File::try_from_dict_without_epilog(self)?;
+        Some(Document {
+            text: file.to_string(),
+        })
+    }
+}
+
+/// `step` is the index in the whole path of the first of `keys`.
+fn set<'a>(
+    arena: &mut dyn Build<'a>,
+    cells: Entries<'a>,
+    keys: &[&'a str],
+    step: usize,
+    value: &'a str,
+) -> Result<Entries<'a>, crate::Error> {
+    let (key, rest) = keys.split_first().expect("never called without keys");
+    if let Some(at) = Value::from(*key).find_linearly_in(cells) {
+        let mut entry = cells[at].get();
+        let message = match (&mut entry.item, rest.is_empty()) {
+            (Item::Text { value: old, .. }, true) => {
+                *old = value.into();
+                None
+            }
+            (Item::Dict { cells: inner, .. }, false) => {
+                *inner = set(arena, inner, rest, step + 1, value)?;
+                None
+            }
+            (_, true) => Some("not a text"),
+            (_, false) => Some("not a dict"),
+        };
+        if let Some(message) = message {
+            return Err(crate::Error::Path { step, message });
+        }
+        cells[at].set(entry);
+        return Ok(cells);
+    }
+    let item = if rest.is_empty() {
+        Item::text(value)
+    } else {
+        Item::dict(set(arena, &[], rest, step + 1, value)?)
+    };
+    for cell in cells {
+        arena.push_entry(cell.get()).map_err(crate::Error::Build)?;
+    }
+    arena
+        .push_entry(Entry {
+            key: (*key).into(),
+            item,
+            ..Entry::default()
+        })
+        .map_err(crate::Error::Build)?;
+    arena
+        .finish_entries(cells.len() + 1)
+        .map_err(crate::Error::Build)
+}
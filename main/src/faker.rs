@@ -0,0 +1,185 @@
+//! replace sensitive looking text with harmless fakes, to share real files as fixtures.
+//!
+//! the replacement is deterministic: a given seed always maps the same original text
+//! to the same fake, so values that matched each other before still match after.
+//! structure and comments are untouched (comments are prose, review them by hand),
+//! and so is opaque text (see [Value::is_opaque]), which is already protected.
+
+use crate::seeded::Seeded;
+use crate::{Entries, File, Item, Items, Value};
+
+/// recognizes one kind of sensitive text, and has some harmless replacements for it.
+#[derive(Clone, Copy, Debug)]
+pub struct Detector {
+    /// short description, e.g. "email"
+    pub name: &'static str,
+    /// decide if a text value (and its key, when inside a dict) should be replaced.
+    pub matches: fn(key: Option<&Value<'_>>, value: &Value<'_>) -> bool,
+    /// the replacements to choose from, must not be empty.
+    pub fakes: &'static [&'static str],
+}
+
+/// single line `local@domain.tld` without any whitespace.
+pub const EMAIL: Detector = Detector {
+    name: "email",
+    matches: |_, value| {
+        let Some(line) = value.only_line() else {
+            return false;
+        };
+        let Some((local, domain)) = line.split_once('@') else {
+            return false;
+        };
+        !local.is_empty()
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && !domain.contains('@')
+            && !line.contains(char::is_whitespace)
+    },
+    fakes: &[
+        "alice@example.com",
+        "bob@example.net",
+        "carol@example.org",
+        "dave@example.com",
+        "erin@example.net",
+        "frank@example.org",
+    ],
+};
+
+/// single line dotted quad, each part `0..=255`.
+pub const IPV4: Detector = Detector {
+    name: "ipv4",
+    matches: |_, value| {
+        let Some(line) = value.only_line() else {
+            return false;
+        };
+        let mut parts = 0;
+        for part in line.split('.') {
+            parts += 1;
+            if part.is_empty() || part.len() > 3 || part.parse::<u8>().is_err() {
+                return false;
+            }
+        }
+        parts == 4
+    },
+    // documentation ranges from RFC 5737
+    fakes: &[
+        "192.0.2.10",
+        "192.0.2.77",
+        "198.51.100.23",
+        "198.51.100.200",
+        "203.0.113.5",
+        "203.0.113.99",
+    ],
+};
+
+/// any single line text under a single line key ending with "name" (ignoring case).
+pub const NAME: Detector = Detector {
+    name: "name",
+    matches: |key, value| {
+        let Some(key) = key.and_then(Value::only_line) else {
+            return false;
+        };
+        key.len() >= 4
+            && key.is_char_boundary(key.len() - 4)
+            && key[key.len() - 4..].eq_ignore_ascii_case("name")
+            && value.only_line().is_some_and(|line| !line.is_empty())
+    },
+    fakes: &[
+        "Alice Example",
+        "Bob Sample",
+        "Carol Placeholder",
+        "Dave Dummy",
+        "Erin Fixture",
+        "Frank Mock",
+    ],
+};
+
+/// all of the detectors above.
+pub const DETECTORS: &[Detector] = &[EMAIL, IPV4, NAME];
+
+/// the settings for one anonymization pass.
+#[derive(Clone, Copy, Debug)]
+pub struct Faker<'d> {
+    /// changing the seed changes which fake replaces which original.
+    pub seed: u64,
+    /// the first detector that matches a value decides its replacement.
+    pub detectors: &'d [Detector],
+}
+impl<'d> Faker<'d> {
+    /// all the [DETECTORS] with the given seed.
+    pub fn new(seed: u64) -> Self {
+        Faker {
+            seed,
+            detectors: DETECTORS,
+        }
+    }
+    /// replace (in place, via the cells) every matching text. returns the count.
+    pub fn file(&self, file: &File<'_>) -> usize {
+        self.entries(file.cells)
+    }
+    /// replace (in place, via the cells) every matching text. returns the count.
+    ///
+    /// only the contents of a list or dict can be changed, a lone text is ignored.
+    pub fn item(&self, item: &Item<'_>) -> usize {
+        match item {
+            Item::Text { .. } => 0,
+            Item::List { cells, .. } => self.items(cells),
+            Item::Dict { cells, .. } => self.entries(cells),
+        }
+    }
+    fn items(&self, cells: Items<'_>) -> usize {
+        let mut count = 0;
+        for cell in cells {
+            let mut item = cell.get();
+            if self.replace(None, &mut item) {
+                cell.set(item);
+                count += 1;
+            } else {
+                count += self.item(&item);
+            }
+        }
+        count
+    }
+    fn entries(&self, cells: Entries<'_>) -> usize {
+        let mut count = 0;
+        for cell in cells {
+            let mut entry = cell.get();
+            if self.replace(Some(&entry.key), &mut entry.item) {
+                cell.set(entry);
+                count += 1;
+            } else {
+                count += self.item(&entry.item);
+            }
+        }
+        count
+    }
+    fn replace(&self, key: Option<&Value<'_>>, item: &mut Item<'_>) -> bool {
+        let Item::Text { value, .. } = item else {
+            return false;
+        };
+        if value.is_opaque() {
+            return false;
+        }
+        let Some(detector) = self.detectors.iter().find(|d| (d.matches)(key, value)) else {
+            return false;
+        };
+        let pick = Seeded::new(self.seed ^ fingerprint(value)).below(detector.fakes.len());
+        *value = detector.fakes[pick].into();
+        true
+    }
+}
+
+/// FNV-1a over the lines, so equal values (however indented) agree.
+fn fingerprint(value: &Value<'_>) -> u64 {
+    let mut hash = 0xCBF2_9CE4_8422_2325u64;
+    for (i, line) in value.lines().enumerate() {
+        if i != 0 {
+            hash = (hash ^ b'\n' as u64).wrapping_mul(0x0100_0000_01B3);
+        }
+        for byte in line.bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3);
+        }
+    }
+    hash
+}
@@ -1,9 +1,13 @@
 //! code for encoding data into the Tindalwic format.
 
 use crate::Value;
-use crate::parse::ParseError;
-use crate::walk::PathError;
-use crate::{Comment, Entry, File, Item};
+use crate::comments::Slot;
+use crate::parse::{EntryStarts, ParseError};
+use crate::walk::{Branch, PathError, Trail};
+use crate::{Comment, Entries, Entry, File, Item};
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 use core::cell::Cell;
 use core::fmt::{Display, Formatter, Result, Write};
@@ -28,23 +32,71 @@ impl Display for ParseError {
         }
     }
 }
+impl<'p> Display for Branch<'p> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        match self {
+            Branch::Item(at) => write!(out, "[{}]", at),
+            Branch::Entry(key) => write!(out, "{{{}}}", key),
+            Branch::Text => out.write_str("Text"),
+            Branch::List => out.write_str("List"),
+            Branch::Dict => out.write_str("Dict"),
+        }
+    }
+}
 impl<'p> Display for PathError<'p> {
     fn fmt(&self, out: &mut Formatter<'_>) -> Result {
         out.write_str("walk (")?;
         for branch in self.failed {
-            match branch {
-                crate::walk::Branch::Item(at) => write!(out, "[{}]", at)?,
-                crate::walk::Branch::Entry(key) => write!(out, "{{{}}}", key)?,
-                crate::walk::Branch::Text => out.write_str("Text")?,
-                crate::walk::Branch::List => out.write_str("List")?,
-                crate::walk::Branch::Dict => out.write_str("Dict")?,
-            }
+            Display::fmt(branch, out)?;
         }
         out.write_str("): ")?;
         out.write_str(self.message)?;
         Ok(())
     }
 }
+impl Display for crate::Error {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        match self {
+            crate::Error::Parse(error) => write!(out, "parse: {error}"),
+            crate::Error::Path { step, message } => write!(out, "walk (step {step}): {message}"),
+            crate::Error::Emit(error) => Display::fmt(error, out),
+            crate::Error::Build(message) => write!(out, "build: {message}"),
+            crate::Error::Usage(message) => out.write_str(message),
+            crate::Error::Write => out.write_str("write failed"),
+            #[cfg(feature = "std")]
+            crate::Error::Io(kind) => write!(out, "io: {kind}"),
+        }
+    }
+}
+impl Display for crate::number::ScalarError {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        match self {
+            crate::number::ScalarError::NotText => out.write_str("not a text"),
+            crate::number::ScalarError::Lines => out.write_str("more than one line"),
+            crate::number::ScalarError::Spelling(name) => write!(out, "not a {name}"),
+        }
+    }
+}
+/// the branches from the root, in the same notation as [PathError].
+impl<'t, 'a> Display for Trail<'t, 'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        if let Some(up) = self.up {
+            Display::fmt(up, out)?;
+        }
+        Display::fmt(&self.branch, out)
+    }
+}
+
+impl Display for Slot {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        out.write_str(match self {
+            Slot::Hashbang => "hashbang",
+            Slot::Prolog => "prolog",
+            Slot::Before => "before",
+            Slot::Epilog => "epilog",
+        })
+    }
+}
 
 /// the string value (without indentation, *not* the encoded form).
 impl<'a> Display for Value<'a> {
@@ -67,18 +119,810 @@ impl<'a> Display for Value<'a> {
 
 impl<'a> Display for File<'a> {
     fn fmt(&self, out: &mut Formatter<'_>) -> Result {
-        Output { out, indent: 0 }.file(self)
+        Output::new(out, 0, EncodeOptions::default()).file(self)
+    }
+}
+
+/// choices for the encoder. the default is what [File] uses for [Display].
+///
+/// (comment placement is not a choice, the format allows only one place for each.)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// end the last line with a newline, like every other line.
+    pub final_newline: bool,
+    /// where to put blank lines.
+    pub gaps: Gaps,
+    /// encode the entries of each dict ordered by key (compared line by line),
+    /// rather than in their stored order. duplicate keys keep their relative order.
+    pub sort_keys: bool,
+    /// if not zero, write each leading tab as this many spaces, for display where
+    /// tabs render badly. not the storage format: `alloc::retab` restores the tabs.
+    pub expand_tabs: usize,
+    /// what ends each line.
+    pub line_ending: LineEnding,
+    /// leave out every comment (except a hashbang, which is for the shell) and every
+    /// gap, for a copy that only machines read.
+    pub strip_comments: bool,
+}
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            final_newline: true,
+            gaps: Gaps::Keep,
+            sort_keys: false,
+            expand_tabs: 0,
+            line_ending: LineEnding::Lf,
+            strip_comments: false,
+        }
+    }
+}
+impl EncodeOptions {
+    /// the same bytes for the same content, however the author formatted it:
+    /// keys sorted, no blank lines. suitable for hashing and signing.
+    pub fn canonical() -> Self {
+        EncodeOptions {
+            final_newline: true,
+            gaps: Gaps::Remove,
+            sort_keys: true,
+            expand_tabs: 0,
+            line_ending: LineEnding::Lf,
+            strip_comments: false,
+        }
+    }
+}
+
+/// the blank line policy for [EncodeOptions].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Gaps {
+    /// only where [Entry::gap] says so.
+    #[default]
+    Keep,
+    /// nowhere.
+    Remove,
+    /// between the outermost entries, nowhere else.
+    TopLevel,
+    /// between the outermost entries, and before every other entry with a `//`
+    /// comment, so that edits to neighbors are separate hunks and merge cleanly.
+    DiffFriendly,
+}
+
+impl Gaps {
+    /// whether `entry`, number `at` in a dict that is `indent` deep, gets a gap.
+    fn wants(self, entry: &Entry<'_>, indent: usize, at: usize) -> bool {
+        match self {
+            Gaps::Keep => entry.gap,
+            Gaps::Remove => false,
+            Gaps::TopLevel => indent == 0 && at != 0,
+            Gaps::DiffFriendly => (indent == 0 || entry.before.is_some()) && at != 0,
+        }
+    }
+}
+impl<'a> File<'a> {
+    /// set [Entry::gap] everywhere according to `policy`, in place, so the file keeps
+    /// those gaps even when encoded with [Gaps::Keep].
+    pub fn normalize_gaps(&self, policy: Gaps) {
+        normalize_gaps(self.cells, 0, policy);
+    }
+}
+fn normalize_gaps(cells: Entries<'_>, indent: usize, policy: Gaps) {
+    for (at, cell) in cells.iter().enumerate() {
+        let mut entry = cell.get();
+        entry.gap = policy.wants(&entry, indent, at);
+        cell.set(entry);
+        normalize_gaps_inside(entry.item, indent + 1, policy);
+    }
+}
+fn normalize_gaps_inside(item: Item<'_>, indent: usize, policy: Gaps) {
+    match item {
+        Item::Text { .. } => {}
+        Item::List { cells, .. } => {
+            for cell in cells {
+                normalize_gaps_inside(cell.get(), indent + 1, policy);
+            }
+        }
+        Item::Dict { cells, .. } => normalize_gaps(cells, indent, policy),
+    }
+}
+
+/// the newline policy for [EncodeOptions].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, the storage format.
+    #[default]
+    Lf,
+    /// `\r\n`, for Windows tools. `alloc::from_crlf` turns it back into [LineEnding::Lf]
+    /// for the parser.
+    CrLf,
+}
+
+/// a [File] that will be encoded according to some [EncodeOptions].
+#[derive(Clone, Copy, Debug)]
+pub struct Formatted<'a> {
+    /// what to encode
+    pub file: File<'a>,
+    /// how to encode it
+    pub options: EncodeOptions,
+}
+impl<'a> Display for Formatted<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        match self.options.line_ending {
+            LineEnding::Lf => self.expanded(out),
+            LineEnding::CrLf => self.expanded(&mut CrLf { out }),
+        }
+    }
+}
+impl<'a> Formatted<'a> {
+    fn expanded(&self, out: &mut impl Write) -> Result {
+        if self.options.expand_tabs == 0 {
+            self.trimmed(out)
+        } else {
+            let mut expanded = Expanded {
+                out,
+                width: self.options.expand_tabs,
+                tabs: Some(0),
+            };
+            self.trimmed(&mut expanded)
+        }
+    }
+    fn trimmed(&self, out: &mut impl Write) -> Result {
+        if self.options.final_newline {
+            Output::new(out, 0, self.options).file(&self.file)
+        } else {
+            let mut trimmed = Trimmed {
+                out,
+                newline: false,
+            };
+            Output::new(&mut trimmed, 0, self.options).file(&self.file)
+        }
+    }
+}
+
+/// holds back each trailing newline until more output arrives (so drops the last).
+struct Trimmed<'o> {
+    out: &'o mut dyn Write,
+    newline: bool,
+}
+impl<'o> Write for Trimmed<'o> {
+    fn write_str(&mut self, s: &str) -> Result {
+        if s.is_empty() {
+            return Ok(());
+        }
+        if self.newline {
+            self.out.write_char('\n')?;
+        }
+        match s.strip_suffix('\n') {
+            Some(rest) => {
+                self.newline = true;
+                self.out.write_str(rest)
+            }
+            None => {
+                self.newline = false;
+                self.out.write_str(s)
+            }
+        }
+    }
+}
+
+/// writes `\r\n` for each `\n`.
+struct CrLf<'o> {
+    out: &'o mut dyn Write,
+}
+impl<'o> Write for CrLf<'o> {
+    fn write_str(&mut self, s: &str) -> Result {
+        let mut lines = s.split('\n');
+        if let Some(first) = lines.next() {
+            self.out.write_str(first)?;
+        }
+        for line in lines {
+            self.out.write_str("\r\n")?;
+            self.out.write_str(line)?;
+        }
+        Ok(())
+    }
+}
+
+/// replaces the tabs at the start of each line with spaces. if the line goes on
+/// with a space, its last tab is kept, so `alloc::retab` can tell them apart.
+struct Expanded<'o> {
+    out: &'o mut dyn Write,
+    width: usize,
+    tabs: Option<usize>, // leading tabs seen so far, None once past them
+}
+impl<'o> Write for Expanded<'o> {
+    fn write_str(&mut self, mut s: &str) -> Result {
+        while !s.is_empty() {
+            match self.tabs {
+                Some(tabs) => {
+                    let Some(rest) = s.strip_prefix('\t') else {
+                        let kept = tabs > 0 && s.starts_with(' ');
+                        for _ in 0..(tabs - kept as usize) * self.width {
+                            self.out.write_char(' ')?;
+                        }
+                        if kept {
+                            self.out.write_char('\t')?;
+                        }
+                        self.tabs = None;
+                        continue;
+                    };
+                    self.tabs = Some(tabs + 1);
+                    s = rest;
+                }
+                None => {
+                    let end = s.find('\n').map_or(s.len(), |newline| {
+                        self.tabs = Some(0);
+                        newline + 1
+                    });
+                    self.out.write_str(&s[..end])?;
+                    s = &s[end..];
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the encoding of a dict containing only this entry.
+impl<'a> Display for Entry<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        Output::new(out, 0, EncodeOptions::default()).entry_in_dict(&Cell::new(*self), 0)
+    }
+}
+
+/// the encoding of a list containing only this item.
+impl<'a> Display for Item<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        Output::new(out, 0, EncodeOptions::default()).item_in_list(&Cell::new(*self))
+    }
+}
+
+/// the encoding of an item as a list member, for splicing into a list at `indent`.
+///
+/// the first line is indented too, every line ends with a newline.
+#[derive(Clone, Copy, Debug)]
+pub struct Fragment<'a> {
+    /// what to encode
+    pub item: Item<'a>,
+    /// the number of TAB chars before each line (at least)
+    pub indent: usize,
+}
+impl<'a> Display for Fragment<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        Output::new(out, self.indent, EncodeOptions::default()).item_in_list(&Cell::new(self.item))
+    }
+}
+
+/// one step of a push-style encoding, see [Emitter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event<'e> {
+    /// the `#!` comment, only as the very first event.
+    Hashbang(Comment<'e>),
+    /// the introduction, only at the start (after any Hashbang) or right after a
+    /// StartList or StartDict.
+    Prolog(Comment<'e>),
+    /// a blank line before the next entry, only in a dict.
+    Gap,
+    /// the `//` comment before the next entry, only in a dict.
+    Before(Comment<'e>),
+    /// a whole text, the key is required in a dict and forbidden in a list.
+    Text {
+        /// the key (in a dict)
+        key: Option<Value<'e>>,
+        /// the string value
+        value: Value<'e>,
+    },
+    /// opens a list context, which the matching End closes.
+    StartList {
+        /// the key (in a dict)
+        key: Option<Value<'e>>,
+    },
+    /// opens a dict context, which the matching End closes.
+    StartDict {
+        /// the key (in a dict)
+        key: Option<Value<'e>>,
+    },
+    /// closes the innermost context.
+    End,
+    /// the comment after the Text or End just before it.
+    Epilog(Comment<'e>),
+}
+
+/// what went wrong in an [Emitter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmitError {
+    /// the events do not make a valid file
+    Order(
+        /// English description of the problem
+        &'static str,
+    ),
+    /// the underlying [Write] failed
+    Write,
+}
+impl core::error::Error for EmitError {}
+impl From<core::fmt::Error> for EmitError {
+    fn from(_: core::fmt::Error) -> Self {
+        EmitError::Write
+    }
+}
+impl Display for EmitError {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        match self {
+            EmitError::Order(message) => write!(out, "emit: {message}"),
+            EmitError::Write => out.write_str("emit: write failed"),
+        }
+    }
+}
+
+/// writes a file as it goes, from a sequence of [Event]s, without building the tree.
+///
+/// the encoding is the same as for a [File] with the same content. contexts can be
+/// nested up to 128 deep.
+pub struct Emitter<'o> {
+    out: &'o mut dyn Write,
+    indent: usize,
+    lists: u128, // one bit per open context, set for a list
+    depth: usize,
+    hashbang_ok: bool,
+    prolog_ok: bool,
+    epilog_ok: bool,
+    pending: u8, // 1 after Gap, 2 after Before
+}
+impl<'o> Emitter<'o> {
+    /// start writing a file into `out`.
+    pub fn new(out: &'o mut dyn Write) -> Self {
+        Emitter {
+            out,
+            indent: 0,
+            lists: 0,
+            depth: 0,
+            hashbang_ok: true,
+            prolog_ok: true,
+            epilog_ok: false,
+            pending: 0,
+        }
+    }
+    fn output<'e>(&mut self) -> Output<'_, 'e> {
+        Output::new(&mut self.out, self.indent, EncodeOptions::default())
+    }
+    fn in_list(&self) -> bool {
+        self.depth > 0 && (self.lists >> (self.depth - 1)) & 1 == 1
+    }
+    fn entry<'e>(&mut self, key: Option<Value<'e>>) -> core::result::Result<(), EmitError> {
+        match (self.in_list(), key) {
+            (true, Some(_)) => Err(EmitError::Order("no keys in a list")),
+            (false, None) => Err(EmitError::Order("key required in a dict")),
+            _ => {
+                self.pending = 0;
+                self.prolog_ok = false;
+                Ok(())
+            }
+        }
+    }
+    fn start<'e>(
+        &mut self,
+        key: Option<Value<'e>>,
+        list: bool,
+    ) -> core::result::Result<(), EmitError> {
+        self.entry(key)?;
+        if self.depth == 128 {
+            return Err(EmitError::Order("nested too deeply"));
+        }
+        self.output()
+            .open(key.as_ref(), if list { "[]" } else { "{}" })?;
+        self.indent += 1;
+        if list {
+            self.lists |= 1 << self.depth;
+        } else {
+            self.lists &= !(1 << self.depth);
+        }
+        self.depth += 1;
+        self.prolog_ok = true;
+        self.epilog_ok = false;
+        Ok(())
+    }
+    /// write the next part of the file.
+    pub fn emit<'e>(&mut self, event: Event<'e>) -> core::result::Result<(), EmitError> {
+        let hashbang_ok = self.hashbang_ok;
+        self.hashbang_ok = false;
+        match event {
+            Event::Hashbang(comment) => {
+                if !hashbang_ok {
+                    return Err(EmitError::Order("hashbang must come first"));
+                }
+                self.output().some_comment("#!", &comment)?;
+            }
+            Event::Prolog(comment) => {
+                if !self.prolog_ok {
+                    return Err(EmitError::Order("prolog must start a context"));
+                }
+                self.prolog_ok = false;
+                self.output().some_comment("#", &comment)?;
+            }
+            Event::Gap | Event::Before(_) if self.in_list() => {
+                return Err(EmitError::Order("gap/before only in a dict"));
+            }
+            Event::Gap => {
+                if self.pending != 0 {
+                    return Err(EmitError::Order("gap must come before the before comment"));
+                }
+                self.pending = 1;
+                self.out.write_char('\n')?;
+            }
+            Event::Before(comment) => {
+                if self.pending == 2 {
+                    return Err(EmitError::Order("one before comment per entry"));
+                }
+                self.pending = 2;
+                self.output().some_comment("//", &comment)?;
+            }
+            Event::Text { key, value } => {
+                self.entry(key)?;
+                let item = Item::Text {
+                    value,
+                    epilog: None,
+                };
+                match key {
+                    None => self.output().item_in_list(&Cell::new(item))?,
+                    Some(key) => {
+                        let entry = Entry {
+                            key,
+                            item,
+                            ..Entry::default()
+                        };
+                        self.output().entry_in_dict(&Cell::new(entry), 0)?
+                    }
+                }
+                self.epilog_ok = true;
+            }
+            Event::StartList { key } => self.start(key, true)?,
+            Event::StartDict { key } => self.start(key, false)?,
+            Event::End => {
+                if self.depth == 0 {
+                    return Err(EmitError::Order("End without Start"));
+                }
+                if self.pending != 0 {
+                    return Err(EmitError::Order("gap/before but no key"));
+                }
+                self.depth -= 1;
+                self.indent -= 1;
+                self.prolog_ok = false;
+                self.epilog_ok = true;
+            }
+            Event::Epilog(comment) => {
+                if !self.epilog_ok {
+                    return Err(EmitError::Order("epilog must follow a Text or End"));
+                }
+                self.epilog_ok = false;
+                self.output().some_comment("#", &comment)?;
+            }
+        }
+        if !matches!(event, Event::Text { .. } | Event::End) {
+            self.epilog_ok = false;
+        }
+        Ok(())
+    }
+    /// check that every context was closed and every gap/before got its entry.
+    pub fn finish(self) -> core::result::Result<(), EmitError> {
+        if self.depth != 0 {
+            Err(EmitError::Order("missing End"))
+        } else if self.pending != 0 {
+            Err(EmitError::Order("gap/before but no key"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// the encoding of `edited`, but with the source text of each outermost entry that
+/// is unchanged from `original` copied as is. so untouched regions stay byte-identical
+/// (even when not formatted the way the encoder would) and only the rest is encoded.
+///
+/// `original` must be an unedited parse of `source`, e.g. from parsing it twice
+/// (edits happen in place via the cells, so a copy of the edited [File] won't do).
+/// when it doesn't match the source, everything is encoded.
+#[derive(Clone, Copy, Debug)]
+pub struct Splice<'a> {
+    /// the text that `original` was parsed from
+    pub source: &'a str,
+    /// the unedited parse
+    pub original: File<'a>,
+    /// the parse after the edits
+    pub edited: File<'a>,
+}
+impl<'a> Display for Splice<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        let Splice {
+            source,
+            original,
+            edited,
+        } = *self;
+        let mut output = Output::new(out, 0, EncodeOptions::default());
+        if EntryStarts::new(source).count() != original.cells.len() {
+            return output.file(&edited);
+        }
+        let head = EntryStarts::new(source).next().unwrap_or(source.len());
+        if edited.hashbang == original.hashbang && edited.prolog == original.prolog {
+            output.out.piece(&source[..head])?;
+        } else {
+            output.comment("#!", &edited.hashbang)?;
+            output.comment("#", &edited.prolog)?;
+        }
+        // a cursor, so that entries in their original order are found in linear time
+        let mut starts = EntryStarts::new(source);
+        let mut cursor = 0usize;
+        for (at, cell) in edited.cells.iter().enumerate() {
+            let entry = cell.get();
+            let unchanged = entry
+                .key
+                .find_linearly_in(original.cells)
+                .filter(|&index| original.cells[index].get() == entry);
+            let Some(index) = unchanged else {
+                output.entry_in_dict(cell, at)?;
+                continue;
+            };
+            if index < cursor {
+                starts = EntryStarts::new(source);
+                cursor = 0;
+            }
+            while cursor < index {
+                starts.next();
+                cursor += 1;
+            }
+            let begin = starts.next().expect("counted above");
+            cursor += 1;
+            let end = starts.clone().next().unwrap_or(source.len());
+            let span = &source[begin..end];
+            output.out.piece(span)?;
+            if !span.ends_with('\n') {
+                output.out.piece("\n")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod chunks {
+    extern crate alloc;
+
+    use super::{EncodeOptions, Output, Sink};
+    use crate::{Comment, Entries, File, Item, Items};
+    use alloc::vec::Vec;
+    use core::fmt::Result;
+
+    struct Pieces<'a>(Vec<&'a str>);
+    impl<'a> Sink<'a> for Pieces<'a> {
+        fn piece(&mut self, piece: &'a str) -> Result {
+            self.0.push(piece);
+            Ok(())
+        }
+    }
+
+    /// a list or dict being encoded: its cells, the next one, and its epilog (which
+    /// comes after them, at the indentation of the list or dict itself).
+    enum Frame<'a> {
+        Entries(Entries<'a>, usize, Option<Comment<'a>>),
+        Items(Items<'a>, usize, Option<Comment<'a>>),
+    }
+
+    /// the encoding of a [File] as a sequence of slices: lines of text borrowed from
+    /// the values, and short separators (indentation, `=`, brackets, newlines).
+    ///
+    /// concatenated, they are the [Display](core::fmt::Display) output. only the
+    /// slices of one entry or item are held at a time (its own lines, not those of
+    /// the entries or items inside it), plus one frame for each level of nesting. so
+    /// writing a huge document needs no buffer of its size.
+    pub struct Chunks<'a> {
+        started: bool,
+        file: File<'a>,
+        stack: Vec<Frame<'a>>,
+        pieces: Pieces<'a>,
+        at: usize,
+    }
+    impl<'a> Chunks<'a> {
+        pub(crate) fn new(file: File<'a>) -> Self {
+            Chunks {
+                started: false,
+                file,
+                stack: alloc::vec![Frame::Entries(file.cells, 0, None)],
+                pieces: Pieces(Vec::new()),
+                at: 0,
+            }
+        }
+        /// the pieces of the next step, false when there are none left.
+        fn refill(&mut self) -> bool {
+            self.pieces.0.clear();
+            self.at = 0;
+            let indent = self.stack.len().saturating_sub(1);
+            let mut output = Output::new(&mut self.pieces, indent, EncodeOptions::default());
+            output.shallow = true;
+            if !self.started {
+                self.started = true;
+                let file = &self.file;
+                output
+                    .comment("#!", &file.hashbang)
+                    .and_then(|_| output.comment("#", &file.prolog))
+                    .expect("collecting never fails");
+                return true;
+            }
+            let (item, done) = match self.stack.last_mut() {
+                None => return false,
+                Some(Frame::Entries(cells, at, _)) if *at < cells.len() => {
+                    *at += 1;
+                    let done = output.entry_in_dict(&cells[*at - 1], *at - 1);
+                    (cells[*at - 1].get().item, done)
+                }
+                Some(Frame::Items(cells, at, _)) if *at < cells.len() => {
+                    *at += 1;
+                    let done = output.item_in_list(&cells[*at - 1]);
+                    (cells[*at - 1].get(), done)
+                }
+                Some(Frame::Entries(_, _, epilog) | Frame::Items(_, _, epilog)) => {
+                    let epilog = *epilog;
+                    self.stack.pop();
+                    output.indent = indent.saturating_sub(1);
+                    output
+                        .comment("#", &epilog)
+                        .expect("collecting never fails");
+                    return true;
+                }
+            };
+            done.expect("collecting never fails");
+            match item {
+                Item::Text { .. } => {}
+                Item::List { cells, epilog, .. } => self.stack.push(Frame::Items(cells, 0, epilog)),
+                Item::Dict { cells, epilog, .. } => {
+                    self.stack.push(Frame::Entries(cells, 0, epilog))
+                }
+            }
+            true
+        }
+    }
+    impl<'a> Iterator for Chunks<'a> {
+        type Item = &'a str;
+        fn next(&mut self) -> Option<&'a str> {
+            while self.at == self.pieces.0.len() {
+                if !self.refill() {
+                    return None;
+                }
+            }
+            self.at += 1;
+            Some(self.pieces.0[self.at - 1])
+        }
+    }
+}
+#[cfg(feature = "alloc")]
+pub use chunks::Chunks;
+
+/// the first lines of the encoding of a [File], then `…` if anything was left out.
+///
+/// a comment is either shown whole or not at all, so a Markdown construct in it is
+/// never cut in half.
+#[derive(Clone, Copy, Debug)]
+pub struct Preview<'a> {
+    /// what to encode
+    pub file: File<'a>,
+    /// the most lines to show (not counting the `…` line)
+    pub max_lines: usize,
+}
+impl<'a> Display for Preview<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        let budget = Budget {
+            left: Cell::new(self.max_lines),
+            cut: Cell::new(false),
+        };
+        let mut limited = Limited {
+            out,
+            budget: &budget,
+        };
+        let mut output = Output::new(&mut limited, 0, EncodeOptions::default());
+        output.budget = Some(&budget);
+        output.file(&self.file)?;
+        if budget.cut.get() {
+            out.write_str("…\n")?;
+        }
+        Ok(())
     }
 }
 
-struct Output<'o, 'f> {
-    out: &'o mut Formatter<'f>,
+/// the lines a [Preview] can still show, shared by [Limited] and [Output].
+struct Budget {
+    left: Cell<usize>,
+    cut: Cell<bool>,
+}
+impl Budget {
+    fn exhaust(&self) {
+        self.left.set(0);
+        self.cut.set(true);
+    }
+}
+
+/// passes whole lines through while the [Budget] lasts.
+struct Limited<'o, 'b> {
+    out: &'o mut dyn Write,
+    budget: &'b Budget,
+}
+impl<'o, 'b> Write for Limited<'o, 'b> {
+    fn write_str(&mut self, s: &str) -> Result {
+        let mut rest = s;
+        while !rest.is_empty() {
+            let left = self.budget.left.get();
+            if left == 0 {
+                self.budget.cut.set(true);
+                return Ok(());
+            }
+            match rest.find('\n') {
+                Some(newline) => {
+                    self.out.write_str(&rest[..=newline])?;
+                    self.budget.left.set(left - 1);
+                    rest = &rest[newline + 1..];
+                }
+                None => {
+                    self.out.write_str(rest)?;
+                    rest = "";
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// a [Write] that only counts.
+struct Counter(usize);
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+fn encoded_len(display: &dyn Display) -> usize {
+    let mut counter = Counter(0);
+    write!(counter, "{display}").expect("counting never fails");
+    counter.0
+}
+impl<'a> File<'a> {
+    /// the exact number of bytes in the encoding, counted without storing it.
+    pub fn encoded_len(&self) -> usize {
+        encoded_len(self)
+    }
+}
+impl<'a> Item<'a> {
+    /// the exact number of bytes in the encoding (as a list member), counted without storing it.
+    pub fn encoded_len(&self) -> usize {
+        encoded_len(self)
+    }
+}
+
+/// where an [Output] goes. the pieces live as long as the values being encoded,
+/// so a consumer can keep them without copying. every [Write] is one.
+trait Sink<'a> {
+    fn piece(&mut self, piece: &'a str) -> Result;
+}
+impl<'a, W: Write + ?Sized> Sink<'a> for W {
+    fn piece(&mut self, piece: &'a str) -> Result {
+        self.write_str(piece)
+    }
+}
+
+struct Output<'o, 'a> {
+    out: &'o mut dyn Sink<'a>,
     indent: usize,
+    options: EncodeOptions,
+    budget: Option<&'o Budget>, // only for a Preview
+    shallow: bool,              // stop after the prolog of a list or dict (for Chunks)
 }
-impl<'o, 'f> Output<'o, 'f> {
+impl<'o, 'a> Output<'o, 'a> {
+    fn new(out: &'o mut dyn Sink<'a>, indent: usize, options: EncodeOptions) -> Self {
+        Output {
+            out,
+            indent,
+            options,
+            budget: None,
+            shallow: false,
+        }
+    }
     fn indent(&mut self) -> Result {
         for _ in 0..self.indent {
-            self.out.write_char('\t')?;
+            self.out.piece("\t")?;
         }
         Ok(())
     }
@@ -88,36 +932,50 @@ impl<'o, 'f> Output<'o, 'f> {
             b'\t' | b'#' | b'<' | b'>' | b'@' | b'[' | b']' | b'{' | b'}' | b'/' | b'='
         )
     }
-    fn string<'a>(&mut self, value: &Value<'a>) -> Result {
+    fn string(&mut self, value: &Value<'a>) -> Result {
         if let Some(slice) = value.verbatim(self.indent) {
-            self.out.write_str(slice)?;
-            self.out.write_char('\n')?;
+            self.out.piece(slice)?;
+            self.out.piece("\n")?;
         } else {
             let mut lines = value.lines();
             if let Some(first) = lines.next() {
-                self.out.write_str(first)?;
-                self.out.write_char('\n')?;
+                self.out.piece(first)?;
+                self.out.piece("\n")?;
                 for line in lines {
                     self.indent()?;
-                    self.out.write_str(line)?;
-                    self.out.write_char('\n')?;
+                    self.out.piece(line)?;
+                    self.out.piece("\n")?;
                 }
             } else {
-                self.out.write_char('\n')?;
+                self.out.piece("\n")?;
             }
         }
         Ok(())
     }
-    fn some_comment<'a>(&mut self, marker: &'a str, comment: &Comment<'a>) -> Result {
+    fn some_comment(&mut self, marker: &'a str, comment: &Comment<'a>) -> Result {
+        if let Some(budget) = self.budget {
+            let value = &comment.value;
+            let lines = if value.is_empty() {
+                1
+            } else if marker == "#" && (value.starts_with('!') || value.starts_with('\n')) {
+                1 + value.lines().count()
+            } else {
+                value.lines().count()
+            };
+            if lines > budget.left.get() {
+                budget.exhaust(); // all or nothing, never a partial comment
+                return Ok(());
+            }
+        }
         self.indent()?;
-        self.out.write_str(marker)?;
+        self.out.piece(marker)?;
         if comment.value.is_empty() {
-            self.out.write_char('\n')?;
+            self.out.piece("\n")?;
         } else {
             self.indent += 1;
             if marker == "#" && (comment.value.starts_with('!') || comment.value.starts_with('\n'))
             {
-                self.out.write_char('\n')?;
+                self.out.piece("\n")?;
                 self.indent()?;
             }
             self.string(&comment.value)?;
@@ -125,25 +983,27 @@ impl<'o, 'f> Output<'o, 'f> {
         }
         Ok(())
     }
-    fn comment<'a>(&mut self, marker: &'a str, option: &Option<Comment<'a>>) -> Result {
+    fn comment(&mut self, marker: &'a str, option: &Option<Comment<'a>>) -> Result {
+        if self.options.strip_comments && marker != "#!" {
+            return Ok(());
+        }
         if let Some(comment) = option {
             self.some_comment(marker, comment)?;
         }
         Ok(())
     }
 
-    fn one_liner_in_list<'a>(value: &Value<'a>) -> Option<&'a str> {
+    fn one_liner_in_list(value: &Value<'a>) -> Option<&'a str> {
         let only = value.only_line()?;
-        if value.is_empty() {
-            Some(only)
-        } else if Output::special_first(only.as_bytes()[0]) {
+        // empty is not allowed: indentation-only is too easily confused with a gap
+        if value.is_empty() || Output::special_first(only.as_bytes()[0]) {
             None
         } else {
             Some(only)
         }
     }
 
-    fn one_liner_in_dict<'a>(value: &Value<'a>, key: &'_ str) -> Option<&'a str> {
+    fn one_liner_in_dict(value: &Value<'a>, key: &'_ str) -> Option<&'a str> {
         let only = value.only_line()?;
         if key.is_empty() {
             Some(only)
@@ -156,16 +1016,38 @@ impl<'o, 'f> Output<'o, 'f> {
         }
     }
 
-    fn item_in_list<'a>(&mut self, cell: &Cell<Item<'a>>) -> Result {
+    /// the line(s) opening a list or dict context, `brackets` is `[]` or `{}`.
+    /// in a list (no key) that is all, in a dict it becomes `[key]` or `@key` + `[]`.
+    fn open(&mut self, key: Option<&Value<'a>>, brackets: &'static str) -> Result {
+        self.indent()?;
+        if let Some(key) = key {
+            if let Some(only) = key.only_line() {
+                self.out.piece(&brackets[..1])?;
+                self.out.piece(only)?;
+                self.out.piece(&brackets[1..])?;
+                return self.out.piece("\n");
+            }
+            self.out.piece("@")?;
+            self.indent += 1;
+            self.string(key)?;
+            self.indent -= 1;
+            self.indent()?;
+        }
+        self.out.piece(brackets)?;
+        self.out.piece("\n")
+    }
+    fn item_in_list(&mut self, cell: &Cell<Item<'a>>) -> Result {
         let item = cell.get();
         match &item {
             Item::Text { value, epilog } => {
                 self.indent()?;
                 if let Some(slice) = Output::one_liner_in_list(value) {
-                    self.out.write_str(slice)?;
-                    self.out.write_char('\n')?;
+                    self.out.piece(slice)?;
+                    self.out.piece("\n")?;
+                } else if value.is_empty() {
+                    self.out.piece("<>\n")?; // zero lines
                 } else {
-                    self.out.write_str("<>\n")?;
+                    self.out.piece("<>\n")?;
                     self.indent += 1;
                     self.indent()?;
                     self.string(value)?;
@@ -178,10 +1060,13 @@ impl<'o, 'f> Output<'o, 'f> {
                 cells,
                 epilog,
             } => {
-                self.indent()?;
-                self.out.write_str("[]\n")?;
+                self.open(None, "[]")?;
                 self.indent += 1;
                 self.comment("#", prolog)?;
+                if self.shallow {
+                    self.indent -= 1;
+                    return Ok(());
+                }
                 for cell in *cells {
                     self.item_in_list(cell)?;
                 }
@@ -193,50 +1078,54 @@ impl<'o, 'f> Output<'o, 'f> {
                 cells,
                 epilog,
             } => {
-                self.indent()?;
-                self.out.write_str("{}\n")?;
+                self.open(None, "{}")?;
                 self.indent += 1;
                 self.comment("#", prolog)?;
-                for cell in *cells {
-                    self.entry_in_dict(cell)?;
+                if self.shallow {
+                    self.indent -= 1;
+                    return Ok(());
                 }
+                self.entries(cells)?;
                 self.indent -= 1;
                 self.comment("#", epilog)
             }
         }
     }
-    fn entry_in_dict<'a>(&mut self, cell: &Cell<Entry<'a>>) -> Result {
+    fn entry_in_dict(&mut self, cell: &Cell<Entry<'a>>, at: usize) -> Result {
         let entry = cell.get();
-        if entry.gap {
+        let gap = !self.options.strip_comments && self.options.gaps.wants(&entry, self.indent, at);
+        if gap {
             // TODO be strict? f.write_indent(self.indent)?;
-            self.out.write_char('\n')?;
+            self.out.piece("\n")?;
         }
         self.comment("//", &entry.before)?;
         match &entry.item {
             Item::Text { value, epilog } => {
                 self.indent()?;
-                if let Some(only) = entry.key.only_line() {
+                // a key with leading spaces would look indented once tabs are expanded
+                let spaced = self.options.expand_tabs != 0 && entry.key.starts_with(' ');
+                if let Some(only) = entry.key.only_line().filter(|_| !spaced) {
                     if let Some(text) = Output::one_liner_in_dict(value, only) {
-                        self.out.write_str(only)?;
-                        self.out.write_char('=')?;
-                        self.out.write_str(text)?;
-                        self.out.write_char('\n')?;
+                        self.out.piece(only)?;
+                        self.out.piece("=")?;
+                        self.out.piece(text)?;
+                        self.out.piece("\n")?;
                     } else {
-                        self.out.write_char('<')?;
-                        self.out.write_str(only)?;
-                        self.out.write_str(">\n")?;
+                        self.out.piece("<")?;
+                        self.out.piece(only)?;
+                        self.out.piece(">\n")?;
                         self.indent += 1;
                         self.indent()?;
                         self.string(value)?;
                         self.indent -= 1;
                     }
                 } else {
-                    self.out.write_char('@')?;
+                    self.out.piece("@")?;
                     self.indent += 1;
                     self.string(&entry.key)?;
                     self.indent -= 1;
                     self.indent()?;
-                    self.out.write_str("<>\n")?;
+                    self.out.piece("<>\n")?;
                     self.indent += 1;
                     self.indent()?;
                     self.string(value)?;
@@ -249,21 +1138,13 @@ impl<'o, 'f> Output<'o, 'f> {
                 cells,
                 epilog,
             } => {
-                self.indent()?;
-                if let Some(only) = entry.key.only_line() {
-                    self.out.write_char('[')?;
-                    self.out.write_str(only)?;
-                    self.out.write_str("]\n")?;
-                } else {
-                    self.out.write_char('@')?;
-                    self.indent += 1;
-                    self.string(&entry.key)?;
-                    self.indent -= 1;
-                    self.indent()?;
-                    self.out.write_str("[]\n")?;
-                }
+                self.open(Some(&entry.key), "[]")?;
                 self.indent += 1;
                 self.comment("#", prolog)?;
+                if self.shallow {
+                    self.indent -= 1;
+                    return Ok(());
+                }
                 for cell in *cells {
                     self.item_in_list(cell)?;
                 }
@@ -275,34 +1156,61 @@ impl<'o, 'f> Output<'o, 'f> {
                 cells,
                 epilog,
             } => {
-                self.indent()?;
-                if let Some(only) = entry.key.only_line() {
-                    self.out.write_char('{')?;
-                    self.out.write_str(only)?;
-                    self.out.write_str("}\n")?;
-                } else {
-                    self.out.write_char('@')?;
-                    self.indent += 1;
-                    self.string(&entry.key)?;
-                    self.indent -= 1;
-                    self.indent()?;
-                    self.out.write_str("{}\n")?;
-                }
+                self.open(Some(&entry.key), "{}")?;
                 self.indent += 1;
                 self.comment("#", prolog)?;
-                for cell in *cells {
-                    self.entry_in_dict(cell)?;
+                if self.shallow {
+                    self.indent -= 1;
+                    return Ok(());
                 }
+                self.entries(cells)?;
                 self.indent -= 1;
                 self.comment("#", epilog)
             }
         }
     }
-    fn file<'a>(&mut self, file: &File<'a>) -> Result {
+    fn file(&mut self, file: &File<'a>) -> Result {
         self.comment("#!", &file.hashbang)?;
         self.comment("#", &file.prolog)?;
-        for cell in file.cells {
-            self.entry_in_dict(cell)?;
+        self.entries(file.cells)
+    }
+    fn entries(&mut self, cells: Entries<'a>) -> Result {
+        if !self.options.sort_keys {
+            for (at, cell) in cells.iter().enumerate() {
+                self.entry_in_dict(cell, at)?;
+            }
+            return Ok(());
+        }
+        self.sorted_entries(cells)
+    }
+    /// with an index to sort: O(n log n).
+    #[cfg(feature = "alloc")]
+    fn sorted_entries(&mut self, cells: Entries<'a>) -> Result {
+        let mut order = alloc::vec::Vec::from_iter(0..cells.len());
+        order.sort_by(|&i, &j| cells[i].get().key.lines().cmp(cells[j].get().key.lines()));
+        for (at, next) in order.into_iter().enumerate() {
+            self.entry_in_dict(&cells[next], at)?;
+        }
+        Ok(())
+    }
+    /// selection without allocating: O(n^2), for no_std (the "alloc" feature sorts).
+    #[cfg(not(feature = "alloc"))]
+    fn sorted_entries(&mut self, cells: Entries<'a>) -> Result {
+        let order = |i: usize, j: usize| {
+            let (a, b) = (cells[i].get().key, cells[j].get().key);
+            a.lines().cmp(b.lines()).then(i.cmp(&j))
+        };
+        let mut last: Option<usize> = None;
+        for at in 0..cells.len() {
+            let next = (0..cells.len())
+                .filter(|&i| match last {
+                    None => true,
+                    Some(last) => order(i, last).is_gt(),
+                })
+                .min_by(|&i, &j| order(i, j))
+                .expect("one unvisited entry for each `at`");
+            self.entry_in_dict(&cells[next], at)?;
+            last = Some(next);
         }
         Ok(())
     }
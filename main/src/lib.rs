@@ -3,6 +3,7 @@
 //! Text in Nested Dictionaries and Lists - with Important Comments
 
 use core::cell::Cell;
+use core::hash::{Hash, Hasher};
 
 #[doc(inline)]
 /// build a [walk::Path]
@@ -16,20 +17,42 @@ pub use tindalwic_macros::path;
 ///  + [path!].walk([File].cells) to the place to be changed,
 ///  + use [json!] to build a new [Item],
 ///  + then use [core::cell::Cell::set] to affect the change.
+///
+/// `///` lines in front of a key become the [Entry::before] comment of that entry.
+/// `#[prolog = ..]` and `#[epilog = ..]` in front of a key or a list item (each
+/// with an expr for a `&str`) become the comments of the item (a text has no
+/// prolog). the outermost list or dict has no place for either.
 pub use tindalwic_macros::json;
 
 #[doc(inline)]
 pub use tindalwic_macros::arena;
 
 pub mod capped;
+pub mod cells;
+pub mod comments;
+pub mod conformance;
+pub mod faker;
 pub mod fmt;
+pub mod number;
 pub mod parse;
+pub mod render;
+pub mod testing;
 pub mod walk;
 
+mod seeded;
+#[cfg(feature = "std")]
+mod sha256;
+
 #[cfg(feature = "alloc")]
 pub mod alloc;
 #[cfg(feature = "bumpalo")]
 pub mod bumpalo;
+#[cfg(feature = "alloc")]
+pub mod cursor;
+#[cfg(all(feature = "std", feature = "bumpalo"))]
+pub mod easy;
+#[cfg(feature = "std")]
+pub mod std;
 
 /// the semver plus the git fingerprint
 pub const VERSION: &str = env!("TINDALWIC_VERSION");
@@ -75,6 +98,27 @@ mod value {
         pub fn starts_with(&self, pat: char) -> bool {
             self.slice.starts_with(pat)
         }
+        /// `true` if it starts with `!enc:`, the mark of ciphertext (or some other
+        /// secret) that every tool must carry byte for byte: never interpolated,
+        /// normalized or faked.
+        pub fn is_opaque(&self) -> bool {
+            self.slice.starts_with("!enc:")
+        }
+        /// the key this value stands for, if it declares an alias like `!alias:port`.
+        /// see [Dict::resolve](crate::cells::Dict::resolve).
+        pub fn alias_of(&self) -> Option<&'a str> {
+            self.only_line()?.strip_prefix("!alias:")
+        }
+        /// a copy of the text in the `arena` (via [Build::intern](crate::parse::Build::intern)),
+        /// so it no longer borrows from the buffer that was parsed.
+        pub fn clone_owned<'b>(
+            &self,
+            arena: &mut dyn crate::parse::Build<'b>,
+        ) -> Result<Value<'b>, &'static str> {
+            let slice = arena.intern(self.slice)?;
+            let indent = self.indent;
+            Ok(Value { slice, indent })
+        }
         /// the format sometimes allows shorter encoding for single line values
         pub fn only_line(&self) -> Option<&'a str> {
             if self.indent == usize::MAX {
@@ -94,6 +138,18 @@ mod value {
                 None
             }
         }
+        /// the number of tabs stored in front of each line after the first (which
+        /// [Value::lines] strips), or None for a single line (where there are none).
+        pub fn indent(&self) -> Option<usize> {
+            (self.indent != usize::MAX).then_some(self.indent)
+        }
+        /// like [Value::lines], but each line as stored, with the tabs of
+        /// [Value::indent] still in front of all but the first. to move the text to
+        /// another indent, swap that many tabs for the new number, without a copy of
+        /// the decoded text.
+        pub fn raw_lines(&self) -> impl Iterator<Item = &'a str> {
+            self.slice.split('\n')
+        }
         /// Returned iterator produces one sub-slice for each line.
         ///
         /// Always produces at least one line. Omits indentation and newline chars.
@@ -172,8 +228,7 @@ mod value {
 }
 pub use value::Value;
 impl<'a> Value<'a> {
-    /// linear `O(n)` scan.
-    // TODO: add link to `alloc` map view, say it "offers `O(1)`."
+    /// linear `O(n)` scan. with the "alloc" feature, `alloc::KeyIndex` offers `O(1)`.
     pub fn find_linearly_in(self, cells: Entries<'_>) -> Option<usize> {
         cells.iter().position(|cell| cell.get().key == self)
     }
@@ -184,12 +239,12 @@ impl<'a> From<&'a str> for Value<'a> {
     }
 }
 impl<'a> Eq for Value<'a> {}
-impl<'a> core::hash::Hash for Value<'a> {
-    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+impl<'a> Hash for Value<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
         let mut lines = self.lines();
         let first = lines.next().expect("lines is never empty");
         first.hash(state);
-        for line in self.lines() {
+        for line in lines {
             b'\n'.hash(state);
             line.hash(state);
         }
@@ -230,7 +285,10 @@ impl<'a> core::hash::Hash for Value<'a> {
 /// assert_eq!(html, "<p>with <del>strikethrough</del> extension</p>");
 /// # }
 /// ```
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+///
+/// with the "gfm" feature, `Comment::to_html` does the same, and
+/// `Comment::to_plain_text` keeps only the words.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Comment<'a> {
     /// the string value
     pub value: Value<'a>,
@@ -242,6 +300,37 @@ impl<'a> Comment<'a> {
             value: value.into(),
         })
     }
+    /// a comment with a copy of `text` in the `arena` (see
+    /// [Build::intern](parse::Build::intern)), so `text` can be a temporary.
+    pub fn interned(arena: &mut dyn parse::Build<'a>, text: &str) -> Result<Self, &'static str> {
+        let value = arena.intern(text)?.into();
+        Ok(Comment { value })
+    }
+    /// replace the text with a copy of `text` in the `arena` (see [Comment::interned]).
+    pub fn set_text(
+        &mut self,
+        arena: &mut dyn parse::Build<'a>,
+        text: &str,
+    ) -> Result<(), &'static str> {
+        *self = Comment::interned(arena, text)?;
+        Ok(())
+    }
+    /// a copy with the text in the `arena` (see [Value::clone_owned]).
+    pub fn clone_owned<'b>(
+        &self,
+        arena: &mut dyn parse::Build<'b>,
+    ) -> Result<Comment<'b>, &'static str> {
+        let value = self.value.clone_owned(arena)?;
+        Ok(Comment { value })
+    }
+}
+fn clone_comment<'b>(
+    comment: &Option<Comment<'_>>,
+    arena: &mut dyn parse::Build<'b>,
+) -> Result<Option<Comment<'b>>, &'static str> {
+    comment
+        .map(|comment| comment.clone_owned(arena))
+        .transpose()
 }
 
 // ------------------------------------------------------------------------------------
@@ -249,7 +338,7 @@ impl<'a> Comment<'a> {
 /// an association (from key to item) and its metadata.
 ///
 /// at the lowest level, these are stored in an array.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Entry<'a> {
     /// a key can have a blank line before it (before its comment)
     pub gap: bool,
@@ -275,6 +364,35 @@ impl<'a> Entry<'a> {
     pub fn array<const N: usize>() -> [Cell<Entry<'a>>; N] {
         ::core::array::from_fn::<_, N, _>(|_| Cell::default())
     }
+    /// associate `key` with an [Item::text].
+    pub fn text(key: &'a str, value: &'a str) -> Self {
+        Entry::new(key, Item::text(value))
+    }
+    /// associate `key` with an [Item::list].
+    pub fn list(key: &'a str, cells: Items<'a>) -> Self {
+        Entry::new(key, Item::list(cells))
+    }
+    /// associate `key` with an [Item::dict].
+    pub fn dict(key: &'a str, cells: Entries<'a>) -> Self {
+        Entry::new(key, Item::dict(cells))
+    }
+    /// associate `key` with `item`, without a gap or comment.
+    pub fn new(key: &'a str, item: Item<'a>) -> Self {
+        Entry {
+            key: key.into(),
+            item,
+            ..Entry::default()
+        }
+    }
+    /// the same, with a blank line before it.
+    pub fn with_gap(self) -> Self {
+        Entry { gap: true, ..self }
+    }
+    /// the same, with a `//` comment before it.
+    pub fn with_before(self, comment: &'a str) -> Self {
+        let before = Comment::some(comment);
+        Entry { before, ..self }
+    }
 }
 
 // ------------------------------------------------------------------------------------
@@ -335,6 +453,198 @@ impl<'a> Item<'a> {
             epilog: None,
         }
     }
+    /// a deep copy: all the text (and comments) in the `arena`, and new cells too.
+    ///
+    /// unlike the shallow [Clone] (which shares the cells, so an edit to one shows in
+    /// both), the copy is independent of `self` and of the buffer it was parsed from.
+    /// for example to put a duplicate under a different key, then change it.
+    pub fn clone_owned<'b>(
+        &self,
+        arena: &mut dyn parse::Build<'b>,
+    ) -> Result<Item<'b>, &'static str> {
+        Ok(match self {
+            Item::Text { value, epilog } => Item::Text {
+                value: value.clone_owned(arena)?,
+                epilog: clone_comment(epilog, arena)?,
+            },
+            Item::List {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                let prolog = clone_comment(prolog, arena)?;
+                for cell in *cells {
+                    let item = cell.get().clone_owned(arena)?;
+                    arena.push_item(item)?;
+                }
+                Item::List {
+                    prolog,
+                    cells: arena.finish_items(cells.len())?,
+                    epilog: clone_comment(epilog, arena)?,
+                }
+            }
+            Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            } => Item::Dict {
+                prolog: clone_comment(prolog, arena)?,
+                cells: clone_entries(cells, arena)?,
+                epilog: clone_comment(epilog, arena)?,
+            },
+        })
+    }
+    /// `true` for an Item::Text with a [Value::is_opaque] value.
+    pub fn is_opaque(&self) -> bool {
+        matches!(self, Item::Text { value, .. } if value.is_opaque())
+    }
+    /// for an Item::Text, the [Value::alias_of] its value.
+    pub fn alias_of(&self) -> Option<&'a str> {
+        match self {
+            Item::Text { value, .. } => value.alias_of(),
+            _ => None,
+        }
+    }
+    /// `true` if the content is the same, ignoring all comments and gaps. (the derived
+    /// `==` compares those too.) entries must be in the same order.
+    pub fn semantic_eq(&self, other: &Item<'_>) -> bool {
+        match (self, other) {
+            (Item::Text { value: a, .. }, Item::Text { value: b, .. }) => a.lines().eq(b.lines()),
+            (Item::List { cells: a, .. }, Item::List { cells: b, .. }) => {
+                a.len() == b.len() && a.iter().zip(*b).all(|(a, b)| a.get().semantic_eq(&b.get()))
+            }
+            (Item::Dict { cells: a, .. }, Item::Dict { cells: b, .. }) => semantic_eq(a, b),
+            _ => false,
+        }
+    }
+    /// `true` for an Item::Text.
+    pub fn is_text(&self) -> bool {
+        matches!(self, Item::Text { .. })
+    }
+    /// `true` for an Item::List.
+    pub fn is_list(&self) -> bool {
+        matches!(self, Item::List { .. })
+    }
+    /// `true` for an Item::Dict.
+    pub fn is_dict(&self) -> bool {
+        matches!(self, Item::Dict { .. })
+    }
+    /// the value of an Item::Text.
+    pub fn as_text(&self) -> Option<Value<'a>> {
+        match self {
+            Item::Text { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+    /// the cells of an Item::List.
+    pub fn as_list(&self) -> Option<Items<'a>> {
+        match self {
+            Item::List { cells, .. } => Some(cells),
+            _ => None,
+        }
+    }
+    /// the cells of an Item::Dict.
+    pub fn as_dict(&self) -> Option<Entries<'a>> {
+        match self {
+            Item::Dict { cells, .. } => Some(cells),
+            _ => None,
+        }
+    }
+    /// the value of an Item::Text, to replace (in a copy taken out of its cell).
+    pub fn as_text_mut(&mut self) -> Option<&mut Value<'a>> {
+        match self {
+            Item::Text { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+    /// replace the value of an Item::Text with a copy of `text` in the `arena` (see
+    /// [Build::intern](parse::Build::intern)), so `text` can be a temporary. keeps
+    /// the epilog. a list or dict is left alone, and is an error.
+    pub fn set_text(
+        &mut self,
+        arena: &mut dyn parse::Build<'a>,
+        text: &str,
+    ) -> Result<(), &'static str> {
+        *self.as_text_mut().ok_or("not a text")? = arena.intern(text)?.into();
+        Ok(())
+    }
+    /// the cells of an Item::List, to replace with a new slice.
+    pub fn as_list_mut(&mut self) -> Option<&mut Items<'a>> {
+        match self {
+            Item::List { cells, .. } => Some(cells),
+            _ => None,
+        }
+    }
+    /// the cells of an Item::Dict, to replace with a new slice.
+    pub fn as_dict_mut(&mut self) -> Option<&mut Entries<'a>> {
+        match self {
+            Item::Dict { cells, .. } => Some(cells),
+            _ => None,
+        }
+    }
+    /// the introductory comment of a list or dict (a text has none).
+    pub fn prolog(&self) -> Option<Comment<'a>> {
+        match self {
+            Item::Text { .. } => None,
+            Item::List { prolog, .. } | Item::Dict { prolog, .. } => *prolog,
+        }
+    }
+    /// the comment after any item.
+    pub fn epilog(&self) -> Option<Comment<'a>> {
+        match self {
+            Item::Text { epilog, .. } | Item::List { epilog, .. } | Item::Dict { epilog, .. } => {
+                *epilog
+            }
+        }
+    }
+    /// replace the introductory comment of a list or dict. a text has no place for
+    /// one, so that is an error (unless `prolog` is None).
+    pub fn set_prolog(&mut self, prolog: Option<Comment<'a>>) -> Result<(), &'static str> {
+        match self {
+            Item::Text { .. } if prolog.is_none() => Ok(()),
+            Item::Text { .. } => Err("a text has no prolog"),
+            Item::List { prolog: old, .. } | Item::Dict { prolog: old, .. } => {
+                *old = prolog;
+                Ok(())
+            }
+        }
+    }
+    /// replace the comment after any item.
+    pub fn set_epilog(&mut self, epilog: Option<Comment<'a>>) {
+        match self {
+            Item::Text { epilog: old, .. }
+            | Item::List { epilog: old, .. }
+            | Item::Dict { epilog: old, .. } => *old = epilog,
+        }
+    }
+    /// leave an empty Item::Text in place of self, and return what was there (see
+    /// [core::mem::take]). for an item in a tree, [Cell::take] does the same.
+    pub fn take(&mut self) -> Item<'a> {
+        core::mem::take(self)
+    }
+    /// put `item` in place of self, and return what was there (see
+    /// [core::mem::replace]). for an item in a tree, [Cell::replace] does the same.
+    pub fn replace(&mut self, item: Item<'a>) -> Item<'a> {
+        core::mem::replace(self, item)
+    }
+    /// for an Item::Dict, the item of the entry with `key`. for reading a few values
+    /// from a known shape, chained with `?` (a [path!] also says where it failed).
+    pub fn by_key(&self, key: &str) -> Option<Item<'a>> {
+        match self {
+            Item::Dict { cells, .. } => {
+                let at = Value::from(key).find_linearly_in(cells)?;
+                Some(cells[at].get().item)
+            }
+            _ => None,
+        }
+    }
+    /// for an Item::List, the item at `index` (see [Item::by_key]).
+    pub fn by_index(&self, index: usize) -> Option<Item<'a>> {
+        match self {
+            Item::List { cells, .. } => Some(cells.get(index)?.get()),
+            _ => None,
+        }
+    }
     /// wrap an array of cells of items into an Item::List
     pub fn list(cells: Items<'a>) -> Self {
         Item::List {
@@ -353,6 +663,89 @@ impl<'a> Item<'a> {
     }
 }
 
+impl<'a> From<&'a str> for Item<'a> {
+    fn from(value: &'a str) -> Self {
+        Item::text(value)
+    }
+}
+impl<'a> From<Value<'a>> for Item<'a> {
+    fn from(value: Value<'a>) -> Self {
+        Item::Text {
+            value,
+            epilog: None,
+        }
+    }
+}
+impl<'a> From<Items<'a>> for Item<'a> {
+    fn from(cells: Items<'a>) -> Self {
+        Item::list(cells)
+    }
+}
+impl<'a> From<Entries<'a>> for Item<'a> {
+    fn from(cells: Entries<'a>) -> Self {
+        Item::dict(cells)
+    }
+}
+/// hashes the content and the comments (see [Item::semantic_hash] for content only).
+///
+/// a list or dict is hashed through its cells, which can be changed through a
+/// shared reference, and then the hash changes too. an item can be a key of a
+/// `HashSet` or `HashMap` (which compares keys with `==` as well), but only while
+/// nothing under it is changed: clippy's `mutable_key_type` warns about that.
+impl<'a> Hash for Item<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+        match self {
+            Item::Text { value, epilog } => (value, epilog).hash(state),
+            Item::List {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                prolog.hash(state);
+                cells.len().hash(state);
+                cells.iter().for_each(|cell| cell.get().hash(state));
+                epilog.hash(state);
+            }
+            Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                prolog.hash(state);
+                cells.len().hash(state);
+                cells.iter().for_each(|cell| cell.get().hash(state));
+                epilog.hash(state);
+            }
+        }
+    }
+}
+impl<'a> Item<'a> {
+    /// hash only what [Item::semantic_eq] compares, so equal content hashes the same
+    /// whatever its comments and gaps.
+    pub fn semantic_hash<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+        match self {
+            Item::Text { value, .. } => value.hash(state),
+            Item::List { cells, .. } => {
+                cells.len().hash(state);
+                cells
+                    .iter()
+                    .for_each(|cell| cell.get().semantic_hash(state));
+            }
+            Item::Dict { cells, .. } => semantic_hash(cells, state),
+        }
+    }
+}
+fn semantic_hash<H: Hasher>(cells: Entries<'_>, state: &mut H) {
+    cells.len().hash(state);
+    for cell in cells {
+        let entry = cell.get();
+        entry.key.hash(state);
+        entry.item.semantic_hash(state);
+    }
+}
+
 // ------------------------------------------------------------------------------------
 
 /// the outermost context.
@@ -368,6 +761,97 @@ pub struct File<'a> {
     pub cells: Entries<'a>,
 }
 impl<'a> File<'a> {
+    /// a deep copy, see [Item::clone_owned].
+    pub fn clone_owned<'b>(
+        &self,
+        arena: &mut dyn parse::Build<'b>,
+    ) -> Result<File<'b>, &'static str> {
+        Ok(File {
+            hashbang: clone_comment(&self.hashbang, arena)?,
+            prolog: clone_comment(&self.prolog, arena)?,
+            cells: clone_entries(self.cells, arena)?,
+        })
+    }
+    /// the same file, starting with `#!` and then `line`, so it can be run (e.g. with
+    /// `/usr/bin/env mytool`). `line` must be one line, without tabs, and not start
+    /// with `!`.
+    pub fn with_hashbang(self, line: &'a str) -> Result<Self, &'static str> {
+        if line.contains('\n') {
+            Err("hashbang must be one line")
+        } else if line.contains('\t') {
+            Err("hashbang must not contain tabs")
+        } else if line.starts_with('!') {
+            Err("hashbang must not start with !")
+        } else {
+            let hashbang = Comment::some(line);
+            Ok(File { hashbang, ..self })
+        }
+    }
+    /// the item of the entry with `key`, to start a chain of [Item::by_key] and
+    /// [Item::by_index].
+    pub fn by_key(&self, key: &str) -> Option<Item<'a>> {
+        self.embed_without_hashbang().by_key(key)
+    }
+    /// parse `content`, giving up at the first error (see [parse::Parse::first_error]).
+    pub fn parse(
+        arena: &mut dyn parse::Parse<'a>,
+        content: &'a str,
+    ) -> Result<Self, parse::ParseError> {
+        arena.first_error(content)
+    }
+    /// the text of the `#!` comment, if there is one.
+    pub fn hashbang(&self) -> Option<Value<'a>> {
+        self.hashbang.map(|comment| comment.value)
+    }
+    /// the text of the introductory comment, if there is one.
+    pub fn prolog(&self) -> Option<Value<'a>> {
+        self.prolog.map(|comment| comment.value)
+    }
+    /// how many outermost entries.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+    /// true if there are no entries (there can still be comments).
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+    /// a copy of the entry with `key`.
+    pub fn get(&self, key: &str) -> Option<Entry<'a>> {
+        let at = Value::from(key).find_linearly_in(self.cells)?;
+        Some(self.cells[at].get())
+    }
+    /// a copy of each entry, in order.
+    pub fn iter(&self) -> impl Iterator<Item = Entry<'a>> {
+        self.cells.iter().map(Cell::get)
+    }
+    /// see [cells::Dict::insert], self.cells is replaced.
+    pub fn insert(
+        &mut self,
+        arena: &mut dyn parse::Build<'a>,
+        index: usize,
+        entry: Entry<'a>,
+    ) -> Result<(), &'static str> {
+        self.cells = cells::Dict::insert(self.cells, arena, index, entry)?;
+        Ok(())
+    }
+    /// see [cells::Dict::remove], self.cells is replaced.
+    pub fn remove(
+        &mut self,
+        arena: &mut dyn parse::Build<'a>,
+        key: &str,
+    ) -> Result<Option<Entry<'a>>, &'static str> {
+        let (cells, removed) = cells::Dict::remove(self.cells, arena, key.into())?;
+        self.cells = cells;
+        Ok(removed)
+    }
+    /// see [Item::semantic_eq], the hashbang and prolog are comments too.
+    pub fn semantic_eq(&self, other: &File<'_>) -> bool {
+        semantic_eq(self.cells, other.cells)
+    }
+    /// see [Item::semantic_hash].
+    pub fn semantic_hash<H: Hasher>(&self, state: &mut H) {
+        semantic_hash(self.cells, state);
+    }
     /// make an [Item::Dict] from self.prolog and self.cells
     pub fn embed_without_hashbang(&self) -> Item<'a> {
         Item::Dict {
@@ -391,4 +875,147 @@ impl<'a> File<'a> {
     }
 }
 
+/// hashes everything, comments too (see [File::semantic_hash] for content only).
+///
+/// the same hazard as for [Item]: do not change the cells while the file is a key.
+impl<'a> Hash for File<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hashbang.hash(state);
+        self.prolog.hash(state);
+        self.cells.len().hash(state);
+        self.cells.iter().for_each(|cell| cell.get().hash(state));
+    }
+}
+
+fn semantic_eq(a: Entries<'_>, b: Entries<'_>) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            let (a, b) = (a.get(), b.get());
+            a.key.lines().eq(b.key.lines()) && a.item.semantic_eq(&b.item)
+        })
+}
+
+fn clone_entries<'b>(
+    cells: Entries<'_>,
+    arena: &mut dyn parse::Build<'b>,
+) -> Result<Entries<'b>, &'static str> {
+    for cell in cells {
+        let entry = cell.get();
+        let entry = Entry {
+            gap: entry.gap,
+            before: clone_comment(&entry.before, arena)?,
+            key: entry.key.clone_owned(arena)?,
+            item: entry.item.clone_owned(arena)?,
+        };
+        arena.push_entry(entry)?;
+    }
+    arena.finish_entries(cells.len())
+}
+
 // ====================================================================================
+
+/// any error from this crate, so that `?` works the same on all of them.
+///
+/// no lifetime, so it can be returned from where the input was borrowed. that is
+/// why a [walk::PathError] is reduced to its message and the step that failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// from the parser
+    Parse(parse::ParseError),
+    /// from a [walk::Path]
+    Path {
+        /// the index of the branch where the walk failed
+        step: usize,
+        /// English description of the problem
+        message: &'static str,
+    },
+    /// from an [fmt::Emitter]
+    Emit(fmt::EmitError),
+    /// from a [parse::Build] (arena) that ran out of room, or a method that needs
+    /// one. there is no conversion from any `&'static str`, so map one explicitly:
+    /// `.map_err(Error::Build)?`.
+    Build(
+        /// English description of the problem
+        &'static str,
+    ),
+    /// a method was called in a way that cannot work, e.g. with nothing to do or on
+    /// the wrong type of item. map a `&'static str` explicitly, as for Build.
+    Usage(
+        /// English description of the problem
+        &'static str,
+    ),
+    /// from a [core::fmt::Write]
+    Write,
+    /// from `std::io`, only the `ErrorKind` of the error so that this stays [Copy].
+    #[cfg(feature = "std")]
+    Io(crate::std::IoErrorKind),
+}
+impl Error {
+    /// which kind of error, without the details.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Parse(parse::ParseError::Syntax { .. }) => ErrorKind::Syntax,
+            Error::Parse(parse::ParseError::Memory(_)) | Error::Build(_) => ErrorKind::Memory,
+            Error::Path { .. } => ErrorKind::Path,
+            Error::Usage(_) => ErrorKind::Usage,
+            Error::Emit(fmt::EmitError::Order(_)) => ErrorKind::Order,
+            Error::Emit(fmt::EmitError::Write) | Error::Write => ErrorKind::Write,
+            #[cfg(feature = "std")]
+            Error::Io(_) => ErrorKind::Io,
+        }
+    }
+}
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Parse(error) => Some(error),
+            Error::Emit(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+impl From<parse::ParseError> for Error {
+    fn from(error: parse::ParseError) -> Self {
+        Error::Parse(error)
+    }
+}
+impl<'p> From<walk::PathError<'p>> for Error {
+    fn from(error: walk::PathError<'p>) -> Self {
+        Error::Path {
+            step: error.failed.len().saturating_sub(1),
+            message: error.message,
+        }
+    }
+}
+impl From<fmt::EmitError> for Error {
+    fn from(error: fmt::EmitError) -> Self {
+        Error::Emit(error)
+    }
+}
+impl From<core::fmt::Error> for Error {
+    fn from(_: core::fmt::Error) -> Self {
+        Error::Write
+    }
+}
+
+/// the kinds of [Error]. these are stable: new kinds may be added, but an error
+/// keeps its kind from one version to the next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// the text is not valid Tindalwic
+    Syntax,
+    /// an arena ran out of room (or was asked for something impossible)
+    Memory,
+    /// a path does not fit the tree
+    Path,
+    /// events for an emitter came in an invalid order
+    Order,
+    /// the output failed
+    Write,
+    /// a method was called in a way that cannot work
+    Usage,
+    /// reading or writing a file failed
+    Io,
+}
@@ -0,0 +1,218 @@
+//! numbers in text, spelled the same way on every machine.
+//!
+//! the format only has text, so these pick one spelling for each number: `.` for the
+//! decimal point, no grouping, `e` for an exponent, and `inf`, `-inf` or `NaN`. the
+//! shortest spelling (the default) reads back to the very same bits (any NaN reads
+//! back as a NaN, but not necessarily the same one).
+
+use crate::parse::Build;
+use crate::{Item, Value};
+use core::fmt::{Display, Result, Write};
+use core::write;
+
+/// how many digits after the decimal point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Precision {
+    /// as few as needed to read back the same number.
+    #[default]
+    Shortest,
+    /// exactly this many (rounded), for display. does not read back exactly.
+    Digits(usize),
+}
+
+/// when to write an exponent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Exponent {
+    /// only for magnitudes from `1e21` up, or below `1e-6` (but not zero).
+    #[default]
+    Auto,
+    /// never, however many zeros that takes.
+    Never,
+    /// always, even `0e0`.
+    Always,
+}
+
+/// the choices for [write_f64].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Format {
+    /// digits after the decimal point
+    pub precision: Precision,
+    /// when to use an exponent
+    pub exponent: Exponent,
+}
+
+/// spell `value` according to `format`.
+pub fn write_f64(out: &mut dyn Write, value: f64, format: Format) -> Result {
+    let magnitude = value.abs();
+    let exponent = match format.exponent {
+        Exponent::Auto => magnitude != 0.0 && !(1e-6..1e21).contains(&magnitude),
+        Exponent::Never => false,
+        Exponent::Always => true,
+    };
+    match (format.precision, exponent) {
+        (Precision::Shortest, false) => write!(out, "{value}"),
+        (Precision::Shortest, true) => write!(out, "{value:e}"),
+        (Precision::Digits(digits), false) => write!(out, "{value:.digits$}"),
+        (Precision::Digits(digits), true) => write!(out, "{value:.digits$e}"),
+    }
+}
+
+/// read a number written by [write_f64] (or any other spelling that Rust accepts,
+/// see [str::parse]). None unless `value` is one line that is a number.
+pub fn parse_f64(value: &Value<'_>) -> Option<f64> {
+    value.only_line()?.parse().ok()
+}
+
+/// read an integer, e.g. `-42`. None unless `value` is one line that is an integer.
+pub fn parse_i64(value: &Value<'_>) -> Option<i64> {
+    value.only_line()?.parse().ok()
+}
+
+/// why a text (or item) is not the scalar that was asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalarError {
+    /// a list or dict, not a text
+    NotText,
+    /// more than one line
+    Lines,
+    /// not a spelling of the type (named here)
+    Spelling(&'static str),
+}
+impl core::error::Error for ScalarError {}
+
+impl<'a> Value<'a> {
+    /// `true` or `false` (exactly those spellings).
+    pub fn as_bool(&self) -> core::result::Result<bool, ScalarError> {
+        scalar(self, "bool")
+    }
+    /// an integer, e.g. `-42` (see [parse_i64]).
+    pub fn as_i64(&self) -> core::result::Result<i64, ScalarError> {
+        scalar(self, "i64")
+    }
+    /// a non-negative integer.
+    pub fn as_u64(&self) -> core::result::Result<u64, ScalarError> {
+        scalar(self, "u64")
+    }
+    /// a number (see [parse_f64]).
+    pub fn as_f64(&self) -> core::result::Result<f64, ScalarError> {
+        scalar(self, "f64")
+    }
+}
+impl<'a> Item<'a> {
+    /// [Value::as_bool] for an Item::Text.
+    pub fn as_bool(&self) -> core::result::Result<bool, ScalarError> {
+        self.as_text().ok_or(ScalarError::NotText)?.as_bool()
+    }
+    /// [Value::as_i64] for an Item::Text.
+    pub fn as_i64(&self) -> core::result::Result<i64, ScalarError> {
+        self.as_text().ok_or(ScalarError::NotText)?.as_i64()
+    }
+    /// [Value::as_u64] for an Item::Text.
+    pub fn as_u64(&self) -> core::result::Result<u64, ScalarError> {
+        self.as_text().ok_or(ScalarError::NotText)?.as_u64()
+    }
+    /// [Value::as_f64] for an Item::Text.
+    pub fn as_f64(&self) -> core::result::Result<f64, ScalarError> {
+        self.as_text().ok_or(ScalarError::NotText)?.as_f64()
+    }
+}
+
+impl<'a> Item<'a> {
+    /// replace the value of an Item::Text with `value` (see [text_i64]), keeping the
+    /// epilog. a list or dict is left alone, and is an error.
+    pub fn set_i64(
+        &mut self,
+        arena: &mut dyn Build<'a>,
+        value: i64,
+    ) -> core::result::Result<(), &'static str> {
+        *self.as_text_mut().ok_or("not a text")? = text_i64(arena, value)?;
+        Ok(())
+    }
+    /// like [Item::set_i64], spelled according to `format` (see [text_f64]).
+    pub fn set_f64(
+        &mut self,
+        arena: &mut dyn Build<'a>,
+        value: f64,
+        format: Format,
+    ) -> core::result::Result<(), &'static str> {
+        *self.as_text_mut().ok_or("not a text")? = text_f64(arena, value, format)?;
+        Ok(())
+    }
+    /// like [Item::set_i64], with `true` or `false` (which need no arena).
+    pub fn set_bool(&mut self, value: bool) -> core::result::Result<(), &'static str> {
+        *self.as_text_mut().ok_or("not a text")? = if value { "true" } else { "false" }.into();
+        Ok(())
+    }
+    /// like [Item::set_i64], with what `value` displays as (at most 512 bytes).
+    pub fn set_display(
+        &mut self,
+        arena: &mut dyn Build<'a>,
+        value: impl Display,
+    ) -> core::result::Result<(), &'static str> {
+        let text = self.as_text_mut().ok_or("not a text")?;
+        let mut buffer = Buffer {
+            bytes: [0; 512],
+            len: 0,
+        };
+        write!(buffer, "{value}").map_err(|_| "text too long")?;
+        *text = arena.intern(buffer.as_str())?.into();
+        Ok(())
+    }
+}
+
+fn scalar<T: core::str::FromStr>(
+    value: &Value<'_>,
+    name: &'static str,
+) -> core::result::Result<T, ScalarError> {
+    let line = value.only_line().ok_or(ScalarError::Lines)?;
+    line.parse().map_err(|_| ScalarError::Spelling(name))
+}
+
+/// spell `value` into the `arena` (which must support [Build::intern]).
+pub fn text_f64<'a>(
+    arena: &mut dyn Build<'a>,
+    value: f64,
+    format: Format,
+) -> core::result::Result<Value<'a>, &'static str> {
+    let mut buffer = Buffer {
+        bytes: [0; 512],
+        len: 0,
+    };
+    write_f64(&mut buffer, value, format).map_err(|_| "number too long")?;
+    Ok(arena.intern(buffer.as_str())?.into())
+}
+
+/// spell `value` into the `arena` (which must support [Build::intern]).
+pub fn text_i64<'a>(
+    arena: &mut dyn Build<'a>,
+    value: i64,
+) -> core::result::Result<Value<'a>, &'static str> {
+    let mut buffer = Buffer {
+        bytes: [0; 512],
+        len: 0,
+    };
+    write!(buffer, "{value}").map_err(|_| "number too long")?;
+    Ok(arena.intern(buffer.as_str())?.into())
+}
+
+/// room on the stack for one number.
+struct Buffer {
+    bytes: [u8; 512],
+    len: usize,
+}
+impl Buffer {
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).expect("only str is written")
+    }
+}
+impl Write for Buffer {
+    fn write_str(&mut self, s: &str) -> Result {
+        let end = self.len + s.len();
+        if end > self.bytes.len() {
+            return Err(core::fmt::Error);
+        }
+        self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
@@ -131,6 +131,77 @@ pub enum Reported {
     Continue,
 }
 
+/// the length of the longest prefix of `content` that ends just before an outermost
+/// entry (or its gap and `//` comment), which means everything in that prefix is
+/// complete: parsing it now gives the same result as parsing it after more bytes
+/// have been appended. zero means more input is needed.
+///
+/// for incremental input (or a fragment embedded in some other format, up to some
+/// offset), parse the prefix and keep the remainder for when more bytes arrive. the
+/// remainder starts with an entry, so it parses like a file without a prolog.
+pub fn complete_prefix(content: &str) -> usize {
+    EntryStarts::new(content).last().unwrap_or(0)
+}
+
+/// the offsets where each outermost entry (including its gap and `//` comment)
+/// begins, found by looking only at the first byte of each line.
+#[derive(Clone)]
+pub(crate) struct EntryStarts<'c> {
+    bytes: &'c [u8],
+    line: usize,           // start of the next line to look at
+    gap: Option<usize>,    // an empty line just above
+    before: Option<usize>, // start of a `//` comment (or its gap) above
+    marker: bool,          // an `@` key is open, so its `<>`/`[]`/`{}` line is next
+}
+impl<'c> EntryStarts<'c> {
+    pub(crate) fn new(content: &'c str) -> Self {
+        EntryStarts {
+            bytes: content.as_bytes(),
+            line: 0,
+            gap: None,
+            before: None,
+            marker: false,
+        }
+    }
+}
+impl<'c> Iterator for EntryStarts<'c> {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        while self.line < self.bytes.len() {
+            let line = self.line;
+            self.line = match self.bytes[line..].iter().position(|&byte| byte == b'\n') {
+                Some(newline) => line + newline + 1,
+                None => self.bytes.len(),
+            };
+            match self.bytes[line] {
+                b'\n' => self.gap = Some(line),
+                b'\t' => {
+                    if self.before.is_none() {
+                        self.gap = None; // was inside something nested
+                    }
+                }
+                b'/' => self.before = Some(self.gap.unwrap_or(line)),
+                b'#' => {
+                    self.gap = None;
+                    self.before = None;
+                }
+                first => {
+                    let start = self.before.or(self.gap).unwrap_or(line);
+                    self.gap = None;
+                    self.before = None;
+                    if self.marker {
+                        self.marker = false;
+                    } else {
+                        self.marker = first == b'@';
+                        return Some(start);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
 /// start at provided offset, count tab chars.
 pub(super) fn indentation(bytes: &[u8], start: usize, limit: usize) -> usize {
     let mut offset = start;
@@ -376,8 +447,7 @@ impl<'a, 'r> Input<'a, 'r> {
             if self.start == self.end || self.tabs != indent {
                 break;
             } else if self.first >= self.end {
-                // indentation-only is the shortcut for empty text
-                // TODO maybe too easily confused with gaps (require explicit `<>`)?
+                // indentation-only is read as empty text (lenient, the encoder uses `<>`)
                 item = Some(self.text(indent, self.end)?);
             } else {
                 let len = self.end - self.first;
@@ -399,7 +469,10 @@ impl<'a, 'r> Input<'a, 'r> {
                     }
                     b'<' => {
                         if len != 2 || bytes[self.end - 1] != b'>' {
-                            self.report(ParseError::at(self.line, "malformed `<>` in list"))?;
+                            self.report(ParseError::at(
+                                self.line,
+                                self.malformed(b'>', "malformed `<>` in list"),
+                            ))?;
                             self.next(indent)?;
                         } else {
                             item = Some(self.text_block(indent)?);
@@ -407,7 +480,10 @@ impl<'a, 'r> Input<'a, 'r> {
                     }
                     b'[' => {
                         if len != 2 || bytes[self.end - 1] != b']' {
-                            self.report(ParseError::at(self.line, "malformed `[]` in list"))?;
+                            self.report(ParseError::at(
+                                self.line,
+                                self.malformed(b']', "malformed `[]` in list"),
+                            ))?;
                             self.next(indent)?;
                         } else {
                             self.next(indent + 1)?;
@@ -416,7 +492,10 @@ impl<'a, 'r> Input<'a, 'r> {
                     }
                     b'{' => {
                         if len != 2 || bytes[self.end - 1] != b'}' {
-                            self.report(ParseError::at(self.line, "malformed `{}` in list"))?;
+                            self.report(ParseError::at(
+                                self.line,
+                                self.malformed(b'}', "malformed `{}` in list"),
+                            ))?;
                             self.next(indent)?;
                         } else {
                             self.next(indent + 1)?;
@@ -448,6 +527,22 @@ impl<'a, 'r> Input<'a, 'r> {
         }
     }
 
+    /// the error for a line that starts with a bracket but does not end with the
+    /// closing one: there are no end-of-line comments (see MANIFESTO.md), so a `#`
+    /// after the closing bracket gets a message of its own.
+    fn malformed(&self, close: u8, message: &'static str) -> &'static str {
+        let line = &self.utf8.as_bytes()[self.first..self.end];
+        let Some(at) = line.iter().rposition(|&byte| byte == close) else {
+            return message;
+        };
+        match line[at + 1..]
+            .iter()
+            .find(|&&byte| byte != b' ' && byte != b'\t')
+        {
+            Some(b'#') => "no end-of-line comments (a `#` must start its own line)",
+            _ => message,
+        }
+    }
     /// previous line opened a dict context, so parse all the lines in it.
     fn dict(&mut self, indent: usize, arena: &mut dyn Build<'a>) -> Option<Item<'a>> {
         Some(Item::Dict {
@@ -492,7 +587,10 @@ impl<'a, 'r> Input<'a, 'r> {
                 }
                 b'<' => {
                     if len < 2 || bytes[self.end - 1] != b'>' {
-                        self.report(ParseError::at(self.line, "malformed `<key>` in dict"))?;
+                        self.report(ParseError::at(
+                            self.line,
+                            self.malformed(b'>', "malformed `<key>` in dict"),
+                        ))?;
                         self.next(indent)?;
                     } else {
                         key = self.utf8[self.first + 1..self.end - 1].into();
@@ -501,7 +599,10 @@ impl<'a, 'r> Input<'a, 'r> {
                 }
                 b'[' => {
                     if len < 2 || bytes[self.end - 1] != b']' {
-                        self.report(ParseError::at(self.line, "malformed `[key]` in dict"))?;
+                        self.report(ParseError::at(
+                            self.line,
+                            self.malformed(b']', "malformed `[key]` in dict"),
+                        ))?;
                         self.next(indent)?;
                     } else {
                         key = self.utf8[self.first + 1..self.end - 1].into();
@@ -539,7 +640,10 @@ impl<'a, 'r> Input<'a, 'r> {
                 }
                 b'{' => {
                     if len < 2 || bytes[self.end - 1] != b'}' {
-                        self.report(ParseError::at(self.line, "malformed `{key}` in dict"))?;
+                        self.report(ParseError::at(
+                            self.line,
+                            self.malformed(b'}', "malformed `{key}` in dict"),
+                        ))?;
                         self.next(indent)?;
                     } else {
                         key = self.utf8[self.first + 1..self.end - 1].into();
@@ -0,0 +1,407 @@
+//! renderings of a [File] that are meant to be read by people, not parsers.
+
+use crate::walk::Branch;
+use crate::{Comment, Entry, File, Item, Value};
+use core::fmt::{Display, Formatter, Result, Write};
+
+/// a linear description, one sentence per line, without any punctuation art.
+///
+/// intended for screen readers and for reading a file aloud, e.g.
+/// `key server, dictionary with 2 entries` ... `end of dictionary server`.
+pub struct Spoken<'a>(pub File<'a>);
+impl<'a> Display for Spoken<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        let Spoken(file) = self;
+        let mut speak = Speak { out };
+        speak.comment("hashbang", &file.hashbang)?;
+        speak.comment("comment", &file.prolog)?;
+        for cell in file.cells {
+            speak.entry(&cell.get())?;
+        }
+        Ok(())
+    }
+}
+
+struct Speak<'o, 'f> {
+    out: &'o mut Formatter<'f>,
+}
+impl<'o, 'f> Speak<'o, 'f> {
+    /// the lines of a value, joined with "; " (so it stays one sentence).
+    fn value(&mut self, value: &Value<'_>) -> Result {
+        for (i, line) in value.lines().enumerate() {
+            if i != 0 {
+                self.out.write_str("; ")?;
+            }
+            self.out.write_str(line)?;
+        }
+        Ok(())
+    }
+    fn comment(&mut self, label: &str, comment: &Option<Comment<'_>>) -> Result {
+        if let Some(comment) = comment {
+            self.out.write_str(label)?;
+            self.out.write_str(": ")?;
+            self.value(&comment.value)?;
+            self.out.write_char('\n')?;
+        }
+        Ok(())
+    }
+    fn entry(&mut self, entry: &Entry<'_>) -> Result {
+        self.comment("comment before", &entry.before)?;
+        self.out.write_str("key ")?;
+        self.value(&entry.key)?;
+        self.out.write_str(", ")?;
+        self.item(&entry.item, Some(&entry.key))
+    }
+    fn count(&mut self, count: usize, one: &str, many: &str) -> Result {
+        match count {
+            0 => writeln!(self.out, "with no {many}"),
+            1 => writeln!(self.out, "with 1 {one}"),
+            _ => writeln!(self.out, "with {count} {many}"),
+        }
+    }
+    fn end(&mut self, kind: &str, key: Option<&Value<'_>>) -> Result {
+        write!(self.out, "end of {kind}")?;
+        if let Some(key) = key {
+            self.out.write_char(' ')?;
+            self.value(key)?;
+        }
+        self.out.write_char('\n')
+    }
+    fn item(&mut self, item: &Item<'_>, key: Option<&Value<'_>>) -> Result {
+        match item {
+            Item::Text { value, epilog } => {
+                let lines = value.lines().count();
+                if value.is_empty() {
+                    self.out.write_str("empty text\n")?;
+                } else if lines == 1 {
+                    self.out.write_str("text: ")?;
+                    self.value(value)?;
+                    self.out.write_char('\n')?;
+                } else {
+                    write!(self.out, "text with {lines} lines: ")?;
+                    self.value(value)?;
+                    self.out.write_char('\n')?;
+                }
+                self.comment("comment after", epilog)
+            }
+            Item::List {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                self.out.write_str("list ")?;
+                self.count(cells.len(), "item", "items")?;
+                self.comment("comment", prolog)?;
+                for (at, cell) in cells.iter().enumerate() {
+                    write!(self.out, "item {}, ", at + 1)?;
+                    self.item(&cell.get(), None)?;
+                }
+                if !cells.is_empty() {
+                    self.end("list", key)?;
+                }
+                self.comment("comment after", epilog)
+            }
+            Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                self.out.write_str("dictionary ")?;
+                self.count(cells.len(), "entry", "entries")?;
+                self.comment("comment", prolog)?;
+                for cell in *cells {
+                    self.entry(&cell.get())?;
+                }
+                if !cells.is_empty() {
+                    self.end("dictionary", key)?;
+                }
+                self.comment("comment after", epilog)
+            }
+        }
+    }
+}
+
+/// reference documentation in Markdown, for config files that are the source of truth.
+///
+/// a heading for each entry (nested entries get deeper headings, down to `######`),
+/// then the comments as prose and each text as a code block. a list item that is a
+/// list or dict gets a heading with its number. with the "gfm" feature,
+/// `Reference::to_html` also renders the Markdown.
+pub struct Reference<'a>(pub File<'a>);
+impl<'a> Display for Reference<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        let Reference(file) = self;
+        let mut document = Document { out, depth: 0 };
+        document.prose(&file.prolog)?;
+        document.entries(file.cells)
+    }
+}
+
+struct Document<'o, 'f> {
+    out: &'o mut Formatter<'f>,
+    depth: usize,
+}
+impl<'o, 'f> Document<'o, 'f> {
+    fn prose(&mut self, comment: &Option<Comment<'_>>) -> Result {
+        if let Some(comment) = comment {
+            for line in comment.value.lines() {
+                writeln!(self.out, "{line}")?;
+            }
+            self.out.write_char('\n')?;
+        }
+        Ok(())
+    }
+    fn heading(&mut self) -> Result {
+        for _ in 0..self.depth.clamp(1, 6) {
+            self.out.write_char('#')?;
+        }
+        self.out.write_char(' ')
+    }
+    fn backticks(&mut self, count: usize) -> Result {
+        for _ in 0..count {
+            self.out.write_char('`')?;
+        }
+        Ok(())
+    }
+    fn code(&mut self, value: &Value<'_>) -> Result {
+        // a fence longer than any run of backticks inside
+        let fence = longest_backticks(value).max(2) + 1;
+        self.backticks(fence)?;
+        self.out.write_char('\n')?;
+        for line in value.lines() {
+            writeln!(self.out, "{line}")?;
+        }
+        self.backticks(fence)?;
+        self.out.write_str("\n\n")
+    }
+    fn key(&mut self, key: &Value<'_>) -> Result {
+        // a code span with more backticks than any run inside. a key that starts or
+        // ends with a backtick is padded with a space at both ends, which Markdown
+        // takes off again.
+        let fence = longest_backticks(key) + 1;
+        let starts = key.lines().next().is_some_and(|line| line.starts_with('`'));
+        let ends = key.lines().last().is_some_and(|line| line.ends_with('`'));
+        let pad = if starts || ends { " " } else { "" };
+        self.backticks(fence)?;
+        self.out.write_str(pad)?;
+        for (i, line) in key.lines().enumerate() {
+            if i > 0 {
+                self.out.write_char(' ')?;
+            }
+            self.out.write_str(line)?;
+        }
+        self.out.write_str(pad)?;
+        self.backticks(fence)
+    }
+    fn entries(&mut self, cells: &[core::cell::Cell<Entry<'_>>]) -> Result {
+        self.depth += 1;
+        for cell in cells {
+            let entry = cell.get();
+            self.heading()?;
+            self.key(&entry.key)?;
+            self.out.write_str("\n\n")?;
+            self.prose(&entry.before)?;
+            self.item(&entry.item)?;
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+    fn item(&mut self, item: &Item<'_>) -> Result {
+        match item {
+            Item::Text { value, epilog } => {
+                self.code(value)?;
+                self.prose(epilog)
+            }
+            Item::List {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                self.prose(prolog)?;
+                self.depth += 1;
+                for (at, cell) in cells.iter().enumerate() {
+                    let item = cell.get();
+                    if !matches!(item, Item::Text { .. }) {
+                        self.heading()?;
+                        writeln!(self.out, "item {}\n", at + 1)?;
+                    }
+                    self.item(&item)?;
+                }
+                self.depth -= 1;
+                self.prose(epilog)
+            }
+            Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                self.prose(prolog)?;
+                self.entries(cells)?;
+                self.prose(epilog)
+            }
+        }
+    }
+}
+
+/// the longest run of backticks in any line of `value`.
+fn longest_backticks(value: &Value<'_>) -> usize {
+    let mut longest = 0;
+    for line in value.lines() {
+        let mut run = 0;
+        for c in line.chars() {
+            run = if c == '`' { run + 1 } else { 0 };
+            longest = longest.max(run);
+        }
+    }
+    longest
+}
+
+#[cfg(feature = "gfm")]
+mod html {
+    extern crate alloc;
+
+    use super::Reference;
+    use alloc::string::{String, ToString};
+
+    impl<'a> Reference<'a> {
+        /// the Markdown, rendered as HTML.
+        pub fn to_html(&self) -> String {
+            markdown::to_html_with_options(&self.to_string(), &markdown::Options::gfm())
+                .expect("only MDX can fail, and that is not enabled")
+        }
+    }
+}
+
+/// an outline of the structure, one line per node, drawn with box characters.
+///
+/// each line has the branch (in [Branch] notation), the kind, the size and which
+/// comments are present, e.g. `├── {port} text: 1 line, 4 bytes (before, epilog)`.
+/// far easier to read than the derived [Debug] for real documents.
+pub struct DebugTree<'a>(pub File<'a>);
+impl<'a> Display for DebugTree<'a> {
+    fn fmt(&self, out: &mut Formatter<'_>) -> Result {
+        let DebugTree(file) = self;
+        let mut draw = Draw {
+            out,
+            depth: 0,
+            more: 0,
+        };
+        draw.out.write_str("file: ")?;
+        draw.size(file.cells.len(), "entry", "entries")?;
+        draw.notes(&[
+            ("hashbang", file.hashbang.is_some()),
+            ("prolog", file.prolog.is_some()),
+        ])?;
+        draw.entries(file.cells)
+    }
+}
+
+struct Draw<'o, 'f> {
+    out: &'o mut Formatter<'f>,
+    depth: usize,
+    more: u128, // one bit per level, set if that level has more siblings to come
+}
+impl<'o, 'f> Draw<'o, 'f> {
+    fn size(&mut self, count: usize, one: &str, many: &str) -> Result {
+        let unit = if count == 1 { one } else { many };
+        write!(self.out, "{count} {unit}")
+    }
+    fn notes(&mut self, notes: &[(&str, bool)]) -> Result {
+        let mut first = true;
+        for (name, present) in notes {
+            if *present {
+                self.out.write_str(if first { " (" } else { ", " })?;
+                self.out.write_str(name)?;
+                first = false;
+            }
+        }
+        if !first {
+            self.out.write_char(')')?;
+        }
+        self.out.write_char('\n')
+    }
+    fn branch(&mut self, branch: Branch<'_>, last: bool) -> Result {
+        for level in 0..self.depth {
+            let more = level < 128 && (self.more >> level) & 1 == 1;
+            self.out.write_str(if more { "│   " } else { "    " })?;
+        }
+        self.out.write_str(if last { "└── " } else { "├── " })?;
+        write!(self.out, "{branch} ")
+    }
+    fn nested(&mut self, last: bool) {
+        if self.depth < 128 {
+            if last {
+                self.more &= !(1 << self.depth);
+            } else {
+                self.more |= 1 << self.depth;
+            }
+        }
+        self.depth += 1;
+    }
+    fn entries(&mut self, cells: &[core::cell::Cell<Entry<'_>>]) -> Result {
+        for (at, cell) in cells.iter().enumerate() {
+            let entry = cell.get();
+            let last = at + 1 == cells.len();
+            self.branch(Branch::Entry(entry.key), last)?;
+            self.item(&entry.item, last, entry.gap, entry.before.is_some())?;
+        }
+        Ok(())
+    }
+    fn item(&mut self, item: &Item<'_>, last: bool, gap: bool, before: bool) -> Result {
+        match item {
+            Item::Text { value, epilog } => {
+                let lines = value.lines().count();
+                let bytes = value.lines().map(str::len).sum::<usize>() + lines - 1;
+                self.out.write_str("text: ")?;
+                self.size(lines, "line", "lines")?;
+                self.out.write_str(", ")?;
+                self.size(bytes, "byte", "bytes")?;
+                self.notes(&[
+                    ("gap", gap),
+                    ("before", before),
+                    ("epilog", epilog.is_some()),
+                ])
+            }
+            Item::List {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                self.out.write_str("list: ")?;
+                self.size(cells.len(), "item", "items")?;
+                self.notes(&[
+                    ("gap", gap),
+                    ("before", before),
+                    ("prolog", prolog.is_some()),
+                    ("epilog", epilog.is_some()),
+                ])?;
+                self.nested(last);
+                for (at, cell) in cells.iter().enumerate() {
+                    let last = at + 1 == cells.len();
+                    self.branch(Branch::Item(at), last)?;
+                    self.item(&cell.get(), last, false, false)?;
+                }
+                self.depth -= 1;
+                Ok(())
+            }
+            Item::Dict {
+                prolog,
+                cells,
+                epilog,
+            } => {
+                self.out.write_str("dict: ")?;
+                self.size(cells.len(), "entry", "entries")?;
+                self.notes(&[
+                    ("gap", gap),
+                    ("before", before),
+                    ("prolog", prolog.is_some()),
+                    ("epilog", epilog.is_some()),
+                ])?;
+                self.nested(last);
+                self.entries(cells)?;
+                self.depth -= 1;
+                Ok(())
+            }
+        }
+    }
+}
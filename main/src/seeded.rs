@@ -0,0 +1,22 @@
+//! a tiny deterministic pseudo-random generator (SplitMix64).
+//!
+//! nothing here is suitable for cryptography. the goal is only to make the same
+//! seed produce the same choices on every platform, without any dependency.
+
+pub(crate) struct Seeded(u64);
+impl Seeded {
+    pub(crate) fn new(seed: u64) -> Self {
+        Seeded(seed)
+    }
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// uniform in `0..bound` (Lemire's multiply-shift, bias is negligible here).
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        ((self.next() as u128 * bound as u128) >> 64) as usize
+    }
+}
@@ -0,0 +1,226 @@
+//! all this stuff is enabled by the "std" feature.
+
+extern crate std;
+
+use crate::sha256::Sha256;
+use crate::{Entry, File};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::string::ToString;
+
+pub(crate) use std::io::ErrorKind as IoErrorKind;
+
+/// the choices for [File::save_with].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// keep the old content (if any) in a copy with an added `.bak` extension.
+    pub backup: bool,
+    /// sync the file and its directory before returning (the default). without it,
+    /// a crash can still lose the new content, though never leave a mix.
+    pub sync: bool,
+    /// also (atomically) write a `.sha256` file next to it, in the format that
+    /// `sha256sum --check` reads. it is written after the document, so after a crash
+    /// in between it can be stale: a mismatch then means the document is newer.
+    pub checksum: bool,
+    /// read the document back after the rename, and fail unless it is the same.
+    pub verify: bool,
+}
+impl Default for SaveOptions {
+    fn default() -> Self {
+        SaveOptions {
+            backup: false,
+            sync: true,
+            checksum: false,
+            verify: false,
+        }
+    }
+}
+
+impl<'a> File<'a> {
+    /// write the encoding to `path` so that it is never left half written: first to a
+    /// temporary file in the same directory, which is synced and then renamed over
+    /// `path`. a crash leaves either the old content or the new, never a mix.
+    pub fn save_atomic(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.save_with(path, SaveOptions::default())
+    }
+    /// like [File::save_atomic], but the old content (if any) is also kept in a copy
+    /// named with an added `.bak` extension, e.g. `app.tindalwic.bak`.
+    pub fn save_atomic_with_backup(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let options = SaveOptions {
+            backup: true,
+            ..SaveOptions::default()
+        };
+        self.save_with(path, options)
+    }
+    /// like [File::save_atomic], with the extras chosen in `options`.
+    pub fn save_with(&self, path: impl AsRef<Path>, options: SaveOptions) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut digest = [0; 32];
+        save(path, options.backup, options.sync, |out| {
+            let mut hashing = Hashing {
+                out,
+                hash: Sha256::new(),
+            };
+            write!(hashing, "{self}")?;
+            digest = hashing.hash.finish();
+            Ok(())
+        })?;
+        if options.verify {
+            let mut hash = Sha256::new();
+            io::copy(
+                &mut fs::File::open(path)?,
+                &mut Hashing {
+                    out: io::sink(),
+                    hash: &mut hash,
+                },
+            )?;
+            if hash.finish() != digest {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the saved file reads back different",
+                ));
+            }
+        }
+        if options.checksum {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let mut sidecar = path.as_os_str().to_os_string();
+            sidecar.push(".sha256");
+            save(Path::new(&sidecar), false, options.sync, |out| {
+                for byte in digest {
+                    write!(out, "{byte:02x}")?;
+                }
+                writeln!(out, "  {name}")
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// add `entry` (with its gap and `//` comment) to the end of the encoded document
+/// at `path` (which is created if missing), the same as `alloc::encode_append`.
+/// nothing already in the file is read (except its last byte) or rewritten.
+pub fn append_entry_to_file(path: impl AsRef<Path>, entry: &Entry<'_>) -> io::Result<()> {
+    let mut out = fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    let mut encoded = entry.to_string();
+    if out.seek(SeekFrom::End(0))? != 0 {
+        let mut last = [0u8];
+        out.seek(SeekFrom::End(-1))?;
+        out.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            encoded.insert(0, '\n');
+        }
+    }
+    out.write_all(encoded.as_bytes())?;
+    out.sync_data()
+}
+
+/// passes everything written to `out`, hashing it on the way.
+struct Hashing<W, H> {
+    out: W,
+    hash: H,
+}
+impl<W: Write, H: core::borrow::BorrowMut<Sha256>> Write for Hashing<W, H> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let written = self.out.write(bytes)?;
+        self.hash.borrow_mut().update(&bytes[..written]);
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+fn save(
+    path: &Path,
+    backup: bool,
+    sync: bool,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    let Some(name) = path.file_name() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no file name"));
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let (temp, out) = create_temp(dir, name)?;
+    // only the temp file made by this call is removed, never one of another call
+    let result = replace(path, dir, &temp, out, backup, sync, write);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp); // best effort, the error to report is the first
+    }
+    result
+}
+
+/// a new file `.{name}.{pid}.{count}.tmp` next to the target, unique to this call
+/// (the count goes up with every call in the process, and a name that is taken,
+/// e.g. left behind by a crash, is skipped).
+fn create_temp(dir: &Path, name: &OsStr) -> io::Result<(PathBuf, fs::File)> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    loop {
+        let mut temp = OsString::from(".");
+        temp.push(name);
+        temp.push(std::format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp = dir.join(temp);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+        {
+            Ok(out) => return Ok((temp, out)),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+fn replace(
+    path: &Path,
+    dir: &Path,
+    temp: &Path,
+    out: fs::File,
+    backup: bool,
+    sync: bool,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    let old = fs::metadata(path).ok();
+    if let Some(old) = &old {
+        out.set_permissions(old.permissions())?;
+    }
+    let mut buffered = BufWriter::new(out);
+    write(&mut buffered)?;
+    let out = buffered.into_inner().map_err(|error| error.into_error())?;
+    if sync {
+        out.sync_all()?;
+    }
+    if backup && old.is_some() {
+        let mut bak = path.as_os_str().to_os_string();
+        bak.push(".bak");
+        fs::copy(path, bak)?;
+    }
+    fs::rename(temp, path)?;
+    #[cfg(unix)]
+    if sync {
+        fs::File::open(dir)?.sync_all()?; // makes the rename itself durable
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+impl From<io::Error> for crate::Error {
+    fn from(error: io::Error) -> Self {
+        crate::Error::Io(error.kind())
+    }
+}
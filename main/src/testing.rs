@@ -0,0 +1,356 @@
+//! pseudo-random (but deterministic) documents, for benchmarks, fuzzing seeds and
+//! load tests. the same seed and size always produce the same bytes.
+//!
+//! with the "proptest" feature, also strategies for property tests.
+
+use crate::fmt::{EmitError, Emitter, Event};
+use crate::seeded::Seeded;
+use crate::{Comment, Value};
+use core::fmt::Write;
+
+/// controls for the documents that [write](fn@write) produces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shape {
+    /// the deepest nesting of lists and dicts (zero means only text entries).
+    pub depth: usize,
+    /// the most entries (or items) in one nested dict (or list), 1 to 32.
+    pub width: usize,
+    /// the chance (in percent) for each possible comment to be present.
+    pub comments: usize,
+    /// the chance (in percent) for each entry to have a gap before it.
+    pub gaps: usize,
+}
+impl Default for Shape {
+    fn default() -> Self {
+        Shape {
+            depth: 4,
+            width: 8,
+            comments: 20,
+            gaps: 10,
+        }
+    }
+}
+
+// keys are taken in order within each dict, so they never repeat
+const KEYS: &[&str] = &[
+    "name",
+    "port",
+    "host",
+    "enabled",
+    "a=b",
+    "@at",
+    "two\nlines",
+    "[bracket]",
+    "path",
+    "user",
+    "",
+    "timeout",
+    "#hash",
+    "retries",
+    "{brace}",
+    "mode",
+    "<angle>",
+    "level",
+    "tags",
+    "//slash",
+    "owner",
+    "version",
+    "limit",
+    "color",
+    "region",
+    "zone",
+    "id",
+    "weight",
+    "kind",
+    "url",
+    "token",
+    "ttl",
+];
+const TEXTS: &[&str] = &[
+    "",
+    "yes",
+    "8080",
+    "localhost",
+    "with spaces",
+    "[not a list]",
+    "{not a dict}",
+    "#not a comment",
+    "=",
+    "multi\nline\ntext",
+    "\tleading tab",
+    "trailing\n",
+    "ünïcödé",
+    "<>",
+    "@",
+    "a=b=c",
+];
+const COMMENTS: &[&str] = &[
+    "",
+    "a short comment",
+    "*Markdown* with `code`",
+    "two\nlines",
+    "!bang",
+    "\nstarts with an empty line",
+    "+ item one\n+ item two",
+];
+
+struct Generator<'o> {
+    emitter: Emitter<'o>,
+    rng: Seeded,
+    shape: Shape,
+    left: usize,
+}
+impl<'o> Generator<'o> {
+    fn chance(&mut self, percent: usize) -> bool {
+        self.rng.below(100) < percent
+    }
+    fn pick(&mut self, pool: &[&'static str]) -> Value<'static> {
+        pool[self.rng.below(pool.len())].into()
+    }
+    fn comment(&mut self, event: fn(Comment<'static>) -> Event<'static>) -> Result<(), EmitError> {
+        if self.chance(self.shape.comments) {
+            let value = self.pick(COMMENTS);
+            self.emitter.emit(event(Comment { value }))?;
+        }
+        Ok(())
+    }
+    /// one entry (with `key`) or list item (without), and everything inside it.
+    fn node(&mut self, key: Option<Value<'static>>, depth: usize) -> Result<(), EmitError> {
+        self.left = self.left.saturating_sub(1);
+        let nested = depth < self.shape.depth && self.left > 0 && self.chance(30);
+        if !nested {
+            let value = self.pick(TEXTS);
+            self.emitter.emit(Event::Text { key, value })?;
+        } else if self.chance(50) {
+            self.emitter.emit(Event::StartList { key })?;
+            self.comment(Event::Prolog)?;
+            for _ in 0..self.count() {
+                if self.left == 0 {
+                    break;
+                }
+                self.node(None, depth + 1)?;
+            }
+            self.emitter.emit(Event::End)?;
+        } else {
+            self.emitter.emit(Event::StartDict { key })?;
+            self.comment(Event::Prolog)?;
+            let count = self.count();
+            self.entries(depth + 1, count)?;
+            self.emitter.emit(Event::End)?;
+        }
+        self.comment(Event::Epilog)
+    }
+    fn count(&mut self) -> usize {
+        1 + self.rng.below(self.shape.width.clamp(1, KEYS.len()))
+    }
+    fn entries(&mut self, depth: usize, count: usize) -> Result<(), EmitError> {
+        for (at, key) in KEYS.iter().enumerate().take(count) {
+            if self.left == 0 {
+                break;
+            }
+            if at != 0 && self.chance(self.shape.gaps) {
+                self.emitter.emit(Event::Gap)?;
+            }
+            self.comment(Event::Before)?;
+            self.node(Some((*key).into()), depth)?;
+        }
+        Ok(())
+    }
+}
+
+/// write a valid document with at most `size` entries and items (at least one).
+///
+/// the outermost dict is not limited by [Shape::width], but keys never repeat, so
+/// there is a limit of 32 entries there. reaching a large `size` needs some depth.
+pub fn write(out: &mut dyn Write, seed: u64, size: usize, shape: Shape) -> Result<(), EmitError> {
+    let mut generator = Generator {
+        emitter: Emitter::new(out),
+        rng: Seeded::new(seed),
+        shape,
+        left: size.max(1),
+    };
+    generator.comment(Event::Prolog)?;
+    generator.entries(0, KEYS.len())?;
+    generator.emitter.finish()
+}
+
+#[cfg(feature = "alloc")]
+mod string {
+    extern crate alloc;
+
+    use super::{Shape, write};
+    use alloc::string::String;
+
+    /// a valid document with about `size` entries and items, in the default [Shape].
+    pub fn generate(seed: u64, size: usize) -> String {
+        let mut out = String::new();
+        write(&mut out, seed, size, Shape::default()).expect("generator makes valid events");
+        out
+    }
+}
+#[cfg(feature = "alloc")]
+pub use string::generate;
+
+#[cfg(feature = "proptest")]
+mod strategies {
+    extern crate alloc;
+
+    use crate::bumpalo::Arena;
+    use crate::fmt::{EmitError, Emitter, Event};
+    use crate::{Comment, Value};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use bumpalo::Bump;
+    use proptest::prelude::*;
+
+    /// the text of a [Value]: any UTF-8, on one to four lines.
+    ///
+    /// the types borrow, so these strategies produce the owned text to make them from.
+    pub fn value() -> impl Strategy<Value = String> {
+        prop::collection::vec(any::<String>(), 1..5).prop_map(|lines| lines.join("\n"))
+    }
+
+    /// the text of a [Comment]: the same as a [value].
+    pub fn comment() -> impl Strategy<Value = String> {
+        value()
+    }
+
+    #[derive(Clone, Debug)]
+    enum Node {
+        Text(String),
+        List(Option<String>, Vec<(Node, Option<String>)>),
+        Dict(Option<String>, Vec<Keyed>),
+    }
+    #[derive(Clone, Debug)]
+    struct Keyed {
+        gap: bool,
+        before: Option<String>,
+        key: String,
+        node: Node,
+        epilog: Option<String>,
+    }
+
+    fn keyed(node: impl Strategy<Value = Node>) -> impl Strategy<Value = Keyed> {
+        let before = prop::option::of(comment());
+        let epilog = prop::option::of(comment());
+        (any::<bool>(), before, value(), node, epilog).prop_map(
+            |(gap, before, key, node, epilog)| Keyed {
+                gap,
+                before,
+                key,
+                node,
+                epilog,
+            },
+        )
+    }
+
+    fn note<'t>(
+        text: &'t Option<String>,
+        event: fn(Comment<'t>) -> Event<'t>,
+    ) -> Option<Event<'t>> {
+        let value = text.as_deref()?.into();
+        Some(event(Comment { value }))
+    }
+
+    struct Writer<'o> {
+        emitter: Emitter<'o>,
+    }
+    impl<'o> Writer<'o> {
+        fn optional(&mut self, event: Option<Event<'_>>) -> Result<(), EmitError> {
+            match event {
+                Some(event) => self.emitter.emit(event),
+                None => Ok(()),
+            }
+        }
+        fn node(&mut self, key: Option<&str>, node: &Node) -> Result<(), EmitError> {
+            let key = key.map(Value::from);
+            match node {
+                Node::Text(value) => {
+                    let value = value.as_str().into();
+                    self.emitter.emit(Event::Text { key, value })
+                }
+                Node::List(prolog, items) => {
+                    self.emitter.emit(Event::StartList { key })?;
+                    self.optional(note(prolog, Event::Prolog))?;
+                    for (item, epilog) in items {
+                        self.node(None, item)?;
+                        self.optional(note(epilog, Event::Epilog))?;
+                    }
+                    self.emitter.emit(Event::End)
+                }
+                Node::Dict(prolog, entries) => {
+                    self.emitter.emit(Event::StartDict { key })?;
+                    self.optional(note(prolog, Event::Prolog))?;
+                    self.entries(entries)?;
+                    self.emitter.emit(Event::End)
+                }
+            }
+        }
+        fn entries(&mut self, entries: &[Keyed]) -> Result<(), EmitError> {
+            for (at, entry) in entries.iter().enumerate() {
+                if entries[..at].iter().any(|other| other.key == entry.key) {
+                    continue; // keys must be unique
+                }
+                if entry.gap {
+                    self.emitter.emit(Event::Gap)?;
+                }
+                self.optional(note(&entry.before, Event::Before))?;
+                self.node(Some(&entry.key), &entry.node)?;
+                self.optional(note(&entry.epilog, Event::Epilog))?;
+            }
+            Ok(())
+        }
+    }
+
+    /// a whole valid document: arbitrary keys, values and comments, nested a few
+    /// levels deep. it shrinks toward fewer and shorter parts.
+    pub fn file() -> impl Strategy<Value = String> {
+        let tree = value()
+            .prop_map(Node::Text)
+            .prop_recursive(4, 48, 6, |node| {
+                let items =
+                    prop::collection::vec((node.clone(), prop::option::of(comment())), 0..6);
+                let entries = prop::collection::vec(keyed(node), 0..6);
+                prop_oneof![
+                    (prop::option::of(comment()), items)
+                        .prop_map(|(prolog, items)| Node::List(prolog, items)),
+                    (prop::option::of(comment()), entries)
+                        .prop_map(|(prolog, entries)| Node::Dict(prolog, entries)),
+                ]
+            });
+        let hashbang = prop::option::of(comment());
+        let prolog = prop::option::of(comment());
+        let entries = prop::collection::vec(keyed(tree), 0..6);
+        (hashbang, prolog, entries).prop_map(|(hashbang, prolog, entries)| {
+            let mut out = String::new();
+            let mut writer = Writer {
+                emitter: Emitter::new(&mut out),
+            };
+            let write = || {
+                writer.optional(note(&hashbang, Event::Hashbang))?;
+                writer.optional(note(&prolog, Event::Prolog))?;
+                writer.entries(&entries)?;
+                writer.emitter.finish()
+            };
+            write().expect("the tree makes valid events");
+            out
+        })
+    }
+
+    /// panic unless `source` parses and then encodes back to exactly the same bytes.
+    pub fn assert_roundtrip(source: &str) {
+        let bump = Bump::new();
+        let mut arena = Arena::new(&bump);
+        let file = match arena.format_errors("source", source, 10) {
+            Ok(file) => file,
+            Err(errors) => panic!("does not parse:\n{errors}"),
+        };
+        assert_eq!(
+            alloc::format!("{file}"),
+            source,
+            "encoding differs from source"
+        );
+    }
+}
+#[cfg(feature = "proptest")]
+pub use strategies::{assert_roundtrip, comment, file, value};
@@ -1,13 +1,18 @@
 //! supporting code for the path! macro.
 //! everything here must be pub so macro can use it,
 //! but using these directly is not recommended.
-//! using path! is much easier.
+//! using path! is much easier. a path only known at run time (typed by a user, say)
+//! can be read from text with `alloc::PathBuf` (with the "alloc" feature).
+//!
+//! also traversals of a whole tree ([File::walk] and [Visitor]), which say where
+//! they are with a [Trail].
 
-use crate::{Entry, Item, Value};
+use crate::comments::Slot;
+use crate::{Comment, Entries, Entry, File, Item, Items, Value};
 use core::cell::Cell;
 
 /// a decision along a walk.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Branch<'p> {
     /// select list item by index
     Item(usize),
@@ -30,6 +35,40 @@ pub struct PathError<'p> {
 }
 impl<'p> core::error::Error for PathError<'p> {}
 
+/// where a traversal is: the last [Branch] taken, linked back up through the stack.
+///
+/// no allocation is needed, each level of a recursive traversal makes one of these
+/// in its own stack frame, pointing at the one made by its caller.
+#[derive(Clone, Copy, Debug)]
+pub struct Trail<'t, 'a> {
+    /// the trail to the parent, None when the parent is the root.
+    pub up: Option<&'t Trail<'t, 'a>>,
+    /// the step from the parent to here, always a Branch::Item or Branch::Entry.
+    pub branch: Branch<'a>,
+}
+impl<'t, 'a> Trail<'t, 'a> {
+    /// the number of steps from the root.
+    pub fn depth(&self) -> usize {
+        let mut depth = 1;
+        let mut up = self.up;
+        while let Some(trail) = up {
+            depth += 1;
+            up = trail.up;
+        }
+        depth
+    }
+    /// `true` if `branches` are exactly the steps taken from the root to `trail`.
+    pub fn follows(trail: Option<&Trail<'_, '_>>, branches: &[Branch<'_>]) -> bool {
+        match (trail, branches.split_last()) {
+            (None, None) => true,
+            (Some(trail), Some((last, rest))) => {
+                trail.branch == *last && Trail::follows(trail.up, rest)
+            }
+            _ => false,
+        }
+    }
+}
+
 /// a sequence of Branch built and used by the walk macro.
 ///
 /// the ENTRY parameter determines the Cell inner type of `Ok` walk:
@@ -41,6 +80,15 @@ pub struct Path<'p, const ENTRY: bool> {
     branches: &'p [Branch<'p>],
 }
 impl<'p, const ENTRY: bool> Path<'p, ENTRY> {
+    /// all the decisions, the last one is always Text|List|Dict.
+    pub fn branches(&self) -> &'p [Branch<'p>] {
+        self.branches
+    }
+    /// the last step (an Item or Entry), the one before the Text|List|Dict. for the
+    /// path to the list or dict that holds it, see `alloc::PathBuf::parent`.
+    pub fn last(&self) -> &'p Branch<'p> {
+        &self.branches[self.branches.len() - 2]
+    }
     /// construct an error indicating the given path step failed
     fn error_at(&self, bad: usize, message: &'static str) -> PathError<'p> {
         PathError {
@@ -168,3 +216,213 @@ impl<'p> Path<'p, true> {
         panic!("impossible because of checks in Path::new");
     }
 }
+
+impl<'a> File<'a> {
+    /// call `visit` for every item, depth first in the order they would be encoded
+    /// (a list or dict comes before the items inside it). the [Trail] says where.
+    pub fn walk(&self, visit: &mut dyn FnMut(&Trail<'_, 'a>, Item<'a>)) {
+        entries(self.cells, None, &mut |trail, item| visit(trail, *item));
+    }
+    /// like [File::walk], but changes that `visit` makes to an item are set in place
+    /// (via the cells), before walking the items inside it.
+    pub fn walk_mut(&self, visit: &mut dyn FnMut(&Trail<'_, 'a>, &mut Item<'a>)) {
+        entries(self.cells, None, visit);
+    }
+    /// call `visit` for every entry (in any dict), to change keys, comments or items
+    /// in place, in one pass. the last branch of the [Trail] has the key from before
+    /// the change. the entries inside are walked after the change.
+    pub fn walk_entries_mut(&self, visit: &mut dyn FnMut(&Trail<'_, 'a>, &mut Entry<'a>)) {
+        entries_mut(self.cells, None, visit);
+    }
+}
+impl<'a> Item<'a> {
+    /// [File::walk_mut] for the items inside this list or dict (this item is a copy,
+    /// so it is not visited). the trails start from here.
+    pub fn walk_mut(&self, visit: &mut dyn FnMut(&Trail<'_, 'a>, &mut Item<'a>)) {
+        match *self {
+            Item::Text { .. } => {}
+            Item::List { cells, .. } => items(cells, None, visit),
+            Item::Dict { cells, .. } => entries(cells, None, visit),
+        }
+    }
+}
+
+type Visit<'v, 'a> = dyn FnMut(&Trail<'_, 'a>, &mut Item<'a>) + 'v;
+type VisitEntry<'v, 'a> = dyn FnMut(&Trail<'_, 'a>, &mut Entry<'a>) + 'v;
+
+fn entries_mut<'a>(
+    cells: Entries<'a>,
+    trail: Option<&Trail<'_, 'a>>,
+    visit: &mut VisitEntry<'_, 'a>,
+) {
+    for cell in cells {
+        let mut entry = cell.get();
+        visit(
+            &Trail {
+                up: trail,
+                branch: Branch::Entry(entry.key),
+            },
+            &mut entry,
+        );
+        cell.set(entry);
+        let here = Trail {
+            up: trail,
+            branch: Branch::Entry(entry.key),
+        };
+        entries_inside(entry.item, &here, visit);
+    }
+}
+
+fn entries_inside<'a>(item: Item<'a>, here: &Trail<'_, 'a>, visit: &mut VisitEntry<'_, 'a>) {
+    match item {
+        Item::Text { .. } => {}
+        Item::List { cells, .. } => {
+            for (at, cell) in cells.iter().enumerate() {
+                let inner = Trail {
+                    up: Some(here),
+                    branch: Branch::Item(at),
+                };
+                entries_inside(cell.get(), &inner, visit);
+            }
+        }
+        Item::Dict { cells, .. } => entries_mut(cells, Some(here), visit),
+    }
+}
+
+fn inside<'a>(item: Item<'a>, here: &Trail<'_, 'a>, visit: &mut Visit<'_, 'a>) {
+    match item {
+        Item::Text { .. } => {}
+        Item::List { cells, .. } => items(cells, Some(here), visit),
+        Item::Dict { cells, .. } => entries(cells, Some(here), visit),
+    }
+}
+
+fn items<'a>(cells: Items<'a>, trail: Option<&Trail<'_, 'a>>, visit: &mut Visit<'_, 'a>) {
+    for (at, cell) in cells.iter().enumerate() {
+        let here = Trail {
+            up: trail,
+            branch: Branch::Item(at),
+        };
+        let mut item = cell.get();
+        visit(&here, &mut item);
+        cell.set(item);
+        inside(item, &here, visit);
+    }
+}
+
+fn entries<'a>(cells: Entries<'a>, trail: Option<&Trail<'_, 'a>>, visit: &mut Visit<'_, 'a>) {
+    for cell in cells {
+        let mut entry = cell.get();
+        let here = Trail {
+            up: trail,
+            branch: Branch::Entry(entry.key),
+        };
+        visit(&here, &mut entry.item);
+        cell.set(entry);
+        inside(entry.item, &here, visit);
+    }
+}
+
+/// callbacks for [File::accept] and [Item::accept], which do the recursion. all of
+/// them do nothing by default.
+///
+/// each one gets the location of its item (None for the [File] or starting item).
+/// comments come in the order they would be encoded: a `before` comment just before
+/// its item, a prolog just after entering, and an epilog after exiting.
+#[allow(unused_variables)]
+pub trait Visitor<'a> {
+    /// an Item::Text.
+    fn visit_text(&mut self, trail: Option<&Trail<'_, 'a>>, value: Value<'a>) {}
+    /// an Item::List, before its items.
+    fn enter_list(&mut self, trail: Option<&Trail<'_, 'a>>, len: usize) {}
+    /// an Item::List, after its items.
+    fn exit_list(&mut self, trail: Option<&Trail<'_, 'a>>) {}
+    /// an Item::Dict, before its entries.
+    fn enter_dict(&mut self, trail: Option<&Trail<'_, 'a>>, len: usize) {}
+    /// an Item::Dict, after its entries.
+    fn exit_dict(&mut self, trail: Option<&Trail<'_, 'a>>) {}
+    /// any comment.
+    fn visit_comment(&mut self, trail: Option<&Trail<'_, 'a>>, slot: Slot, comment: Comment<'a>) {}
+}
+
+impl<'a> File<'a> {
+    /// call the methods of `visitor` for everything in the file.
+    pub fn accept(&self, visitor: &mut dyn Visitor<'a>) {
+        comment(visitor, None, Slot::Hashbang, self.hashbang);
+        visitor.enter_dict(None, self.cells.len());
+        comment(visitor, None, Slot::Prolog, self.prolog);
+        accept_entries(self.cells, None, visitor);
+        visitor.exit_dict(None);
+    }
+}
+impl<'a> Item<'a> {
+    /// call the methods of `visitor` for this item and everything inside it.
+    pub fn accept(&self, visitor: &mut dyn Visitor<'a>) {
+        accept(*self, None, visitor);
+    }
+}
+
+fn comment<'a>(
+    visitor: &mut dyn Visitor<'a>,
+    trail: Option<&Trail<'_, 'a>>,
+    slot: Slot,
+    comment: Option<Comment<'a>>,
+) {
+    if let Some(comment) = comment {
+        visitor.visit_comment(trail, slot, comment);
+    }
+}
+
+fn accept<'a>(item: Item<'a>, trail: Option<&Trail<'_, 'a>>, visitor: &mut dyn Visitor<'a>) {
+    let epilog = match item {
+        Item::Text { value, epilog } => {
+            visitor.visit_text(trail, value);
+            epilog
+        }
+        Item::List {
+            prolog,
+            cells,
+            epilog,
+        } => {
+            visitor.enter_list(trail, cells.len());
+            comment(visitor, trail, Slot::Prolog, prolog);
+            for (at, cell) in cells.iter().enumerate() {
+                let here = Trail {
+                    up: trail,
+                    branch: Branch::Item(at),
+                };
+                accept(cell.get(), Some(&here), visitor);
+            }
+            visitor.exit_list(trail);
+            epilog
+        }
+        Item::Dict {
+            prolog,
+            cells,
+            epilog,
+        } => {
+            visitor.enter_dict(trail, cells.len());
+            comment(visitor, trail, Slot::Prolog, prolog);
+            accept_entries(cells, trail, visitor);
+            visitor.exit_dict(trail);
+            epilog
+        }
+    };
+    comment(visitor, trail, Slot::Epilog, epilog);
+}
+
+fn accept_entries<'a>(
+    cells: Entries<'a>,
+    trail: Option<&Trail<'_, 'a>>,
+    visitor: &mut dyn Visitor<'a>,
+) {
+    for cell in cells {
+        let entry = cell.get();
+        let here = Trail {
+            up: trail,
+            branch: Branch::Entry(entry.key),
+        };
+        comment(visitor, Some(&here), Slot::Before, entry.before);
+        accept(entry.item, Some(&here), visitor);
+    }
+}
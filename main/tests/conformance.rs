@@ -0,0 +1,48 @@
+#![allow(missing_docs)]
+#![cfg(feature = "bumpalo")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tindalwic::bumpalo::Arena;
+use tindalwic::conformance::{Case, GRAMMAR};
+
+fn cases() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+    let mut inputs = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "tindalwic") {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    inputs
+}
+
+#[test]
+fn grammar_is_shipped() {
+    assert!(GRAMMAR.contains("root = [hashbang] [comment] *dict-item"));
+}
+
+#[test]
+fn conformance() {
+    let inputs = cases();
+    assert!(!inputs.is_empty(), "no conformance cases found");
+    let mut failed = Vec::new();
+    for path in inputs {
+        let input = fs::read_to_string(&path).unwrap();
+        let tree = fs::read_to_string(path.with_extension("tree")).unwrap();
+        let encoded = fs::read_to_string(path.with_extension("encoded")).ok();
+        let bump = bumpalo::Bump::new();
+        let mut arena = Arena::new(&bump);
+        let case = Case {
+            input: &input,
+            tree: &tree,
+            encoded: encoded.as_deref(),
+        };
+        if let Err(failure) = case.run(&mut arena) {
+            failed.push(format!("{}: {failure}", path.display()));
+        }
+    }
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
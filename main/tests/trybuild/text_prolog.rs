@@ -0,0 +1,6 @@
+use tindalwic::json;
+fn main() {
+    json! {
+        let entries = {"name": #[prolog = "no room"] "app"}.unwrap();
+    }
+}
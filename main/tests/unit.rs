@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 #[cfg(feature = "alloc")]
 use tindalwic::alloc::from_literal;
+use tindalwic::cells::{Dict as _, List as _};
 use tindalwic::parse::Parse as _;
 use tindalwic::{Comment, Entry, File, Item, Value, arena, json, path};
 
@@ -47,6 +48,24 @@ fn hashbang_avoidance() {
     );
 }
 
+#[test]
+fn empty_text_in_list() {
+    json! {
+        let entries = {"l":["","x"],"k":""}.unwrap();
+    }
+    let file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    let encoded = file.to_string();
+    assert_eq!(encoded, "[l]\n\t<>\n\tx\nk=\n");
+    arena! {
+        let mut arena = <2list,2dict>;
+    }
+    let parsed = arena.panic_first_error(&encoded);
+    assert_eq!(parsed.cells, entries);
+}
+
 #[test]
 #[cfg(feature = "alloc")]
 fn three_blank_comments() {
@@ -88,202 +107,2664 @@ fn text_stretch_bug() {
 }
 
 #[test]
-fn two_lines() {
-    json! {
-        let entries = {"key":"one\ntwo"}.unwrap();
-    }
+fn two_lines() {
+    json! {
+        let entries = {"key":"one\ntwo"}.unwrap();
+    }
+    assert_eq!(
+        File::try_from_dict_without_epilog(&Item::dict(entries))
+            .unwrap()
+            .to_string(),
+        "<key>\n\tone\n\ttwo\n"
+    );
+}
+
+#[test]
+fn display_fragments() {
+    json! {
+        let entries = {"k":["v","<"]}.unwrap();
+    }
+    let entry = entries[0].get();
+    assert_eq!(format!("{entry}"), "[k]\n\tv\n\t<>\n\t\t<\n");
+    assert_eq!(entry.item.to_string(), "[]\n\tv\n\t<>\n\t\t<\n");
+    assert_eq!(Item::text("t").to_string(), "t\n");
+    assert_eq!(entry.item.encoded_len(), entry.item.to_string().len());
+    let file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    assert_eq!(file.encoded_len(), file.to_string().len());
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn encode_options() {
+    use tindalwic::fmt::{EncodeOptions, Gaps};
+    let content = "a=1\n\n{b}\n\tc=2\n\n\td=3\ne=4\n";
+    arena! {
+        let mut arena = <0list,5dict>;
+    }
+    let file = arena.panic_first_error(content);
+    let mut options = EncodeOptions::default();
+    assert_eq!(file.encode_with(options), content);
+    options.final_newline = false;
+    assert_eq!(file.encode_with(options), &content[..content.len() - 1]);
+    options.gaps = Gaps::Remove;
+    assert_eq!(file.encode_with(options), "a=1\n{b}\n\tc=2\n\td=3\ne=4");
+    options.gaps = Gaps::TopLevel;
+    options.final_newline = true;
+    assert_eq!(
+        file.encode_with(options),
+        "a=1\n\n{b}\n\tc=2\n\td=3\n\ne=4\n"
+    );
+    let content = "a=1\n{b}\n\tc=2\n\t//about d\n\td=3\n\t//about e\n\te=4\n//about f\nf=5\n";
+    arena! {
+        let mut arena = <0list,7dict>;
+    }
+    let file = arena.panic_first_error(content);
+    options.gaps = Gaps::DiffFriendly;
+    assert_eq!(
+        file.encode_with(options),
+        "a=1\n\n{b}\n\tc=2\n\n\t//about d\n\td=3\n\n\t//about e\n\te=4\n\n//about f\nf=5\n"
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn expand_tabs() {
+    use tindalwic::alloc::retab;
+    use tindalwic::fmt::EncodeOptions;
+    use tindalwic::testing::generate;
+    let options = EncodeOptions {
+        expand_tabs: 2,
+        ..Default::default()
+    };
+    let content = "{a}\n\t[b]\n\t\t  x\n\t\t<>\n\t\t\t\ty\n\t\t\t  z\n   k=v\n";
+    let spaced = "{a}\n  [b]\n  \t  x\n    <>\n        y\n    \t  z\n@   k\n<>\n  v\n";
+    for seed in 0..21 {
+        let content = match seed {
+            20 => String::from(content),
+            _ => generate(seed, 100),
+        };
+        let bump = bumpalo::Bump::new();
+        let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+        let file = arena.panic_first_error(&content);
+        let expanded = file.encode_with(options);
+        if seed == 20 {
+            assert_eq!(expanded, spaced);
+        }
+        let retabbed = retab(&expanded, 2);
+        let again = arena.panic_first_error(&retabbed);
+        assert_eq!(again.to_string(), content, "seed {seed}");
+    }
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn line_ending() {
+    use tindalwic::alloc::from_crlf;
+    use tindalwic::fmt::{EncodeOptions, LineEnding};
+    use tindalwic::testing::generate;
+    let options = EncodeOptions {
+        line_ending: LineEnding::CrLf,
+        ..Default::default()
+    };
+    for seed in 0..21 {
+        let content = match seed {
+            20 => String::from("<a\r>\n\tb\r\n\tc\r\n"),
+            _ => generate(seed, 100),
+        };
+        let bump = bumpalo::Bump::new();
+        let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+        let file = arena.panic_first_error(&content);
+        let windows = file.encode_with(options);
+        assert_eq!(
+            windows.matches('\n').count(),
+            windows.matches("\r\n").count()
+        );
+        let unix = from_crlf(&windows);
+        let again = arena.panic_first_error(&unix);
+        assert_eq!(again.to_string(), content, "seed {seed}");
+    }
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("a=1\nb=2\n");
+    let options = EncodeOptions {
+        final_newline: false,
+        ..options
+    };
+    assert_eq!(file.encode_with(options), "a=1\r\nb=2");
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn encode_canonical() {
+    arena! {
+        let mut arena = <0list,10dict>;
+    }
+    let one = arena.panic_first_error("b=2\n\n{a}\n\tz=1\n\ty=0\n//c\nc=3\n");
+    let two = arena.panic_first_error("{a}\n\ty=0\n\tz=1\n//c\nc=3\nb=2\n");
+    assert_eq!(one.encode_canonical(), "{a}\n\ty=0\n\tz=1\nb=2\n//c\nc=3\n");
+    assert_eq!(one.encode_canonical(), two.encode_canonical());
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn encode_fragment() {
+    json! {
+        let entries = {"k":["v","w\nx"]}.unwrap();
+    }
+    let file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    assert_eq!(file.encode(), "[k]\n\tv\n\t<>\n\t\tw\n\t\tx\n");
+    assert_eq!(
+        entries[0].get().item.encode_fragment(2),
+        "\t\t[]\n\t\t\tv\n\t\t\t<>\n\t\t\t\tw\n\t\t\t\tx\n"
+    );
+}
+
+#[test]
+fn spoken() {
+    json! {
+        let entries = {"server":{"port":"80","hosts":["a","b\nc"]},"empty":""}.unwrap();
+    }
+    let mut file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    file.prolog = Comment::some("settings");
+    let spoken = tindalwic::render::Spoken(file).to_string();
+    assert_eq!(
+        spoken,
+        "comment: settings\n\
+         key server, dictionary with 2 entries\n\
+         key port, text: 80\n\
+         key hosts, list with 2 items\n\
+         item 1, text: a\n\
+         item 2, text with 2 lines: b; c\n\
+         end of list hosts\n\
+         end of dictionary server\n\
+         key empty, empty text\n"
+    );
+}
+
+#[test]
+fn debug_tree() {
+    json! {
+        let entries = {"server":{"port":"80","hosts":["a","b\nc"]},"empty":""}.unwrap();
+    }
+    let mut file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    file.prolog = Comment::some("settings");
+    let mut server = file.cells[0].get();
+    server.before = Comment::some("main one");
+    file.cells[0].set(server);
+    let tree = tindalwic::render::DebugTree(file).to_string();
+    assert_eq!(
+        tree,
+        "file: 2 entries (prolog)\n\
+         ├── {server} dict: 2 entries (before)\n\
+         │   ├── {port} text: 1 line, 2 bytes\n\
+         │   └── {hosts} list: 2 items\n\
+         │       ├── [0] text: 1 line, 1 byte\n\
+         │       └── [1] text: 2 lines, 3 bytes\n\
+         └── {empty} text: 1 line, 0 bytes\n"
+    );
+}
+
+#[test]
+fn emitter() {
+    use tindalwic::fmt::{EmitError, Emitter, Event};
+    let mut out = String::new();
+    let mut emitter = Emitter::new(&mut out);
+    let events = [
+        Event::Prolog(Comment {
+            value: "intro".into(),
+        }),
+        Event::Text {
+            key: Some("a".into()),
+            value: "1".into(),
+        },
+        Event::Epilog(Comment {
+            value: "one".into(),
+        }),
+        Event::Gap,
+        Event::Before(Comment {
+            value: "list".into(),
+        }),
+        Event::StartList {
+            key: Some("b\nc".into()),
+        },
+        Event::Text {
+            key: None,
+            value: "[x]".into(),
+        },
+        Event::StartDict { key: None },
+        Event::Text {
+            key: Some("d".into()),
+            value: "".into(),
+        },
+        Event::End,
+        Event::End,
+        Event::Epilog(Comment {
+            value: "done".into(),
+        }),
+    ];
+    for event in events {
+        emitter.emit(event).unwrap();
+    }
+    emitter.finish().unwrap();
+    assert_eq!(
+        out,
+        "#intro\na=1\n#one\n\n//list\n@b\n\tc\n[]\n\t<>\n\t\t[x]\n\t{}\n\t\td=\n#done\n"
+    );
+    arena! {
+        let mut arena = <2list,3dict>;
+    }
+    assert_eq!(arena.panic_first_error(&out).to_string(), out);
+    let mut out = String::new();
+    let mut emitter = Emitter::new(&mut out);
+    assert_eq!(
+        emitter.emit(Event::Text {
+            key: None,
+            value: "v".into()
+        }),
+        Err(EmitError::Order("key required in a dict"))
+    );
+    emitter
+        .emit(Event::StartDict {
+            key: Some("k".into()),
+        })
+        .unwrap();
+    assert_eq!(emitter.finish(), Err(EmitError::Order("missing End")));
+}
+
+#[test]
+fn preview() {
+    use tindalwic::fmt::Preview;
+    let content = "a=1\n//* one\n\t* two\nb=2\nc=3\n";
+    arena! {
+        let mut arena = <0list,3dict>;
+    }
+    let file = arena.panic_first_error(content);
+    let preview = |max_lines| Preview { file, max_lines }.to_string();
+    assert_eq!(preview(9), content);
+    assert_eq!(preview(5), content);
+    assert_eq!(preview(4), "a=1\n//* one\n\t* two\nb=2\n…\n");
+    assert_eq!(preview(2), "a=1\n…\n");
+    assert_eq!(preview(0), "…\n");
+}
+
+#[test]
+fn splice() {
+    use tindalwic::fmt::Splice;
+    let source = "#\n\tintro\na=1\n<b>\n\tone line\n[c]\n\tx";
+    arena! {
+        let mut arena = <2list,6dict>;
+    }
+    let original = arena.panic_first_error(source);
+    let edited = arena.panic_first_error(source);
+    let cell = path!({"c"}[0]Text)
+        .walk(edited.embed_without_hashbang())
+        .unwrap();
+    cell.set(Item::text("y"));
+    let splice = Splice {
+        source,
+        original,
+        edited,
+    };
+    assert_eq!(
+        splice.to_string(),
+        "#\n\tintro\na=1\n<b>\n\tone line\n[c]\n\ty\n"
+    );
+    assert_eq!(edited.to_string(), "#intro\na=1\nb=one line\n[c]\n\ty\n");
+}
+
+#[test]
+fn multi_line_key() {
+    arena! {
+        let mut arena = <4dict,1list>;
+    }
+    let data = "@one\n\ttwo\n<>\n\tv\n@l\n\t\n[]\n@d\n\t\n{}\n";
+    let file = arena.panic_first_error(data);
+    assert_eq!(file.to_string(), data);
+    let report = &mut |err| {
+        print!("{err}");
+        tindalwic::parse::Reported::Continue
+    };
+    assert!(arena.report_errors("@", report).is_none());
+    assert!(arena.report_errors("@k", report).is_none());
+    assert!(arena.report_errors("@k\n", report).is_none());
+    assert!(arena.report_errors("@k\n<", report).is_none());
+    assert!(arena.report_errors("@k\n<>", report).is_some());
+    assert!(arena.report_errors("@k\n<x>", report).is_none());
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn unified_errors() {
+    use tindalwic::parse::Parse as _;
+    use tindalwic::{Error, ErrorKind};
+    fn count(content: &str) -> Result<usize, Error> {
+        let bump = bumpalo::Bump::new();
+        let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+        let file = arena.first_error(content)?.embed_without_hashbang();
+        let cell = path!({"data"}List).walk(file)?;
+        let Item::List { cells, .. } = cell.get().item else {
+            unreachable!("walk checked")
+        };
+        let mut out = String::new();
+        std::fmt::Write::write_str(&mut out, "counted")?;
+        Ok(cells.len())
+    }
+    assert_eq!(count("[data]\n\tzero\n\tone\n").unwrap(), 2);
+    let error = count("[data\n").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Syntax);
+    assert!(error.to_string().starts_with("parse: 1: error: "));
+    let error = count("data=0\n").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Path);
+    assert_eq!(error.to_string(), "walk (step 1): wrong type of item");
+    assert_eq!(Error::Build("no room for item").kind(), ErrorKind::Memory);
+    assert_eq!(Error::Usage("no keys").kind(), ErrorKind::Usage);
+    #[cfg(feature = "std")]
+    {
+        let error = Error::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert_eq!(error, Error::Io(std::io::ErrorKind::NotFound));
+        assert_eq!(error.kind(), ErrorKind::Io);
+    }
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn walk_error() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena
+        .panic_first_error("[data]\n\tzero\n\t{}\n\t\tk=v")
+        .embed_without_hashbang();
+    path!({"data"}List).walk(file).unwrap();
+    path!({"data"}[0]List).walk(file).unwrap_err();
+    path!({"data"}[0]Text).walk(file).unwrap();
+    path!({"data"}[1]{"x"}Text).walk(file).unwrap_err();
+    path!({"data"}[1]{"k"}Text).walk(file).unwrap();
+    assert_eq!(
+        path!({"data"}[7]Text).walk(file).unwrap_err().to_string(),
+        "walk ({data}[7]): index out of bounds"
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn path_parse() {
+    use tindalwic::alloc::PathBuf;
+    use tindalwic::walk::Branch;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena
+        .panic_first_error("[servers]\n\t{}\n\t\tport=80\n\t\ta.b=dotted\n")
+        .embed_without_hashbang();
+    let arena = arena.builder();
+    let path = PathBuf::parse(arena, ".servers[0].port", Branch::Text).unwrap();
+    assert!(path.item_path().is_none());
+    let cell = path.entry_path().unwrap().walk(file).unwrap();
+    assert_eq!(cell.get().item, Item::text("80"));
+    let path = PathBuf::parse(arena, ".servers[0].a\\.b", Branch::Text).unwrap();
+    assert_eq!(path.branches()[2], Branch::Entry("a.b".into()));
+    let path = PathBuf::parse(arena, ".servers[0]", Branch::Dict).unwrap();
+    assert!(path.item_path().unwrap().walk(file).is_ok());
+    for bad in ["", "servers", ".servers[x]", ".servers[0", ".a\\"] {
+        assert!(PathBuf::parse(arena, bad, Branch::Text).is_err(), "{bad}");
+    }
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn path_buf() {
+    use tindalwic::alloc::PathBuf;
+    use tindalwic::walk::Branch;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena
+        .panic_first_error("{a}\n\t[b]\n\t\tx\n\t\ty\n")
+        .embed_without_hashbang();
+    let mut path = PathBuf::new(Branch::Text);
+    assert!(path.entry_path().is_none() && path.pop().is_none());
+    path.push(Branch::Entry("a".into()));
+    path.push(Branch::Entry("b".into()));
+    path.push(Branch::Item(1));
+    let cell = path.item_path().unwrap().walk(file).unwrap();
+    assert_eq!(cell.get(), Item::text("y"));
+    assert_eq!(path.pop(), Some(Branch::Item(1)));
+    assert_eq!(path.steps().len(), 2);
+    assert!(path.starts_with(&PathBuf::from(&path!({"a"}Dict))));
+    assert!(!PathBuf::from(&path!({"a"}[0]Text)).starts_with(&path));
+    let index = PathBuf::parse(arena.builder(), "[0]", Branch::Text).unwrap();
+    let joined = path.join(&index);
+    assert_eq!(joined.steps().len(), 3);
+    assert_eq!(
+        joined.item_path().unwrap().walk(file).unwrap().get(),
+        Item::text("x")
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn path_pattern() {
+    use tindalwic::alloc::PathPattern;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error(
+        "{services}\n\t{web}\n\t\timage=nginx\n\t\tport=80\n\t{db}\n\t\timage=postgres\n\t\t[ports]\n\t\t\t5432\n*=star\n",
+    );
+    let arena = arena.builder();
+    let mut found = |pattern| {
+        PathPattern::parse(arena, pattern)
+            .unwrap()
+            .matches(&file)
+            .map(|(_, item)| item.as_text().unwrap().joined())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(found(".services.*.image"), ["nginx", "postgres"]);
+    assert_eq!(found(".**.port"), ["80"]);
+    assert_eq!(found(".**.ports[*]"), ["5432"]);
+    assert_eq!(found(".\\*"), ["star"]);
+    let embedded = file.embed_without_hashbang();
+    let pattern = PathPattern::parse(arena, ".services.db.*").unwrap();
+    for (path, item) in pattern.matches(&file) {
+        let cell = path.entry_path().unwrap().walk(embedded).unwrap();
+        assert_eq!(cell.get().item, item);
+    }
+    assert_eq!(pattern.matches(&file).count(), 2);
+    assert!(PathPattern::parse(arena, "").is_err());
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn path_set() {
+    use tindalwic::alloc::{PathBuf, Scaffold};
+    use tindalwic::walk::Branch;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let mut file = arena.panic_first_error("//the server\n{server}\n\tport=80\nname=\n");
+    let arena = arena.builder();
+    let mut set = |text, scaffold| {
+        let path = PathBuf::parse(arena, text, Branch::Text)?;
+        path.set(arena, &mut file, Item::text("x"), scaffold)
+    };
+    set(".server.port", Scaffold::Nothing).unwrap();
+    assert_eq!(set(".server.host", Scaffold::Nothing), Err("key not found"));
+    assert_eq!(
+        set(".hosts[1]", Scaffold::Dicts),
+        Err("index out of bounds")
+    );
+    assert_eq!(
+        set(".name.first", Scaffold::Everything),
+        Err("wrong type of item")
+    );
+    set(".server.tls.cert", Scaffold::Dicts).unwrap();
+    set(".hosts[1].name", Scaffold::Everything).unwrap();
+    let path = PathBuf::parse(arena, ".server", Branch::Text).unwrap();
+    let wrong = path.set(arena, &mut file, Item::list(&[]), Scaffold::Nothing);
+    assert_eq!(wrong, Err("wrong type of item"));
+    let expect = [
+        "//the server",
+        "{server}",
+        "\tport=x",
+        "\t{tls}",
+        "\t\tcert=x",
+        "name=",
+        "[hosts]",
+        "\t<>",
+        "\t{}",
+        "\t\tname=x",
+        "",
+    ];
+    assert_eq!(file.to_string(), expect.join("\n"));
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn path_remove() {
+    use tindalwic::alloc::PathBuf;
+    use tindalwic::walk::Branch;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let content = "top=1\n{server}\n\t//the port\n\tport=80\n\t[hosts]\n\t\ta\n\t\tb\n";
+    let mut file = arena.panic_first_error(content);
+    let arena = arena.builder();
+    let port = PathBuf::parse(arena, ".server.port", Branch::Text).unwrap();
+    let hosts = PathBuf::parse(arena, ".server.hosts[0]", Branch::Text).unwrap();
+    let top = PathBuf::parse(arena, ".top", Branch::Dict).unwrap();
+    assert!(port.remove_item(arena, &mut file).is_err());
+    assert_eq!(
+        top.remove_entry(arena, &mut file),
+        Err("wrong type of item")
+    );
+    let removed = port.remove_entry(arena, &mut file).unwrap();
+    assert_eq!(removed.before, Comment::some("the port"));
+    assert_eq!(removed.item, Item::text("80"));
+    assert_eq!(port.remove_entry(arena, &mut file), Err("key not found"));
+    assert_eq!(hosts.remove_item(arena, &mut file), Ok(Item::text("a")));
+    assert_eq!(file.to_string(), "top=1\n{server}\n\t[hosts]\n\t\tb\n");
+    let top = PathBuf::parse(arena, ".top", Branch::Text).unwrap();
+    top.remove_entry(arena, &mut file).unwrap();
+    assert_eq!(file.to_string(), "{server}\n\t[hosts]\n\t\tb\n");
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn path_parent() {
+    use tindalwic::alloc::PathBuf;
+    use tindalwic::walk::Branch;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena
+        .panic_first_error("{a}\n\t[b]\n\t\tx\n")
+        .embed_without_hashbang();
+    let arena = arena.builder();
+    assert_eq!(path!({"a"}{"b"}List).last(), &Branch::Entry("b".into()));
+    let path = PathBuf::parse(arena, ".a.b[0]", Branch::Text).unwrap();
+    assert_eq!(path.last(), Some(&Branch::Item(0)));
+    let parent = path.parent().unwrap();
+    assert_eq!(parent.branches().last(), Some(&Branch::List));
+    assert!(parent.entry_path().unwrap().walk(file).is_ok());
+    let grandparent = parent.parent().unwrap();
+    assert_eq!(grandparent.last(), Some(&Branch::Entry("a".into())));
+    assert_eq!(grandparent.branches().last(), Some(&Branch::Dict));
+    let root = grandparent.parent().unwrap();
+    assert!(root.last().is_none() && root.parent().is_none());
+}
+
+#[test]
+fn nested_lists() {
+    json! {
+        let items = [[[["value"]]]].unwrap();
+    }
+    let mut array = Entry::array::<1>();
+    array[0].get_mut().item = Item::list(items);
+    let file = File {
+        cells: &array[..],
+        ..Default::default()
+    };
+    assert_eq!(
+        file.to_string(),
+        "[]\n\t[]\n\t\t[]\n\t\t\t[]\n\t\t\t\tvalue\n"
+    );
+    let cell = path!({""}[0][0][0][0]Text)
+        .walk(file.embed_without_hashbang())
+        .unwrap();
+    let Item::Text { value, .. } = cell.get() else {
+        unreachable!("this destructuring always succeeds because path walk did");
+    };
+    assert_eq!(Vec::from_iter(value.lines()), vec!["value"]);
+}
+
+#[test]
+fn nested_dicts() {
+    json! {
+        let entries = {"1":"one","2":["two"],"a":{"b":{"c":{"d":{"k":"v"}}}}}.unwrap();
+    }
+    let dict = Item::dict(entries);
+    let mut keys = Vec::new();
+    for entry in entries {
+        let entry = entry.get();
+        keys.push(entry.key.lines().next().unwrap_or(""));
+    }
+    assert_eq!(keys, vec!["1", "2", "a"]);
+    assert_eq!(
+        File::try_from_dict_without_epilog(&dict)
+            .unwrap()
+            .to_string(),
+        "1=one\n[2]\n\ttwo\n{a}\n\t{b}\n\t\t{c}\n\t\t\t{d}\n\t\t\t\tk=v\n"
+    );
+    let cell = path!({"a"}{"b"}{"c"}{"d"}{"k"}Text).walk(dict).unwrap();
+    let Item::Text { value, .. } = cell.get().item else {
+        unreachable!("this destructuring always succeeds because path walk did");
+    };
+    assert_eq!(Vec::from_iter(value.lines()), vec!["v"]);
+}
+
+#[test]
+fn change_in_list() {
+    json! {
+        let entries = {"a":{"b":["v"]}}.unwrap();
+    }
+    let dict = Item::dict(entries);
+    let cell = path!({"a"}{"b"}[0]Text).walk(dict).unwrap();
+    let Item::Text { value, .. } = cell.get() else {
+        unreachable!("this destructuring always succeeds because path walk did");
+    };
+    let epilog = Comment::some("c");
+    cell.set(Item::Text { value, epilog });
+    assert_eq!(
+        File::try_from_dict_without_epilog(&dict)
+            .unwrap()
+            .to_string(),
+        "{a}\n\t[b]\n\t\tv\n\t\t#c\n"
+    );
+}
+
+#[test]
+fn change_in_dict() {
+    json! {
+        let entries = {"a":[{"b":"z"}]}.unwrap();
+    }
+    let dict = Item::dict(entries);
+    let cell = path!({"a"}[0]{"b"}Text).walk(dict).unwrap();
+    let mut entry = cell.get();
+    entry.item = Item::text("c");
+    cell.set(entry);
+    assert_eq!(
+        File::try_from_dict_without_epilog(&dict)
+            .unwrap()
+            .to_string(),
+        "[a]\n\t{}\n\t\tb=c\n"
+    );
+}
+
+#[test]
+fn inject_comments() {
+    json! {
+        let entries = {"k":"v"}.unwrap();
+    }
+    let dict = Item::dict(entries);
+    let cell = path!({"k"}Text).walk(dict).unwrap();
+    let mut entry = cell.get();
+    let Item::Text { value, .. } = entry.item else {
+        unreachable!("this destructuring always succeeds because path walk did");
+    };
+    let epilog = Comment::some("c");
+    entry.before = Comment::some("b");
+    entry.item = Item::Text { value, epilog };
+    cell.set(entry);
+    assert_eq!(
+        File::try_from_dict_without_epilog(&dict)
+            .unwrap()
+            .to_string(),
+        "//b\nk=v\n#c\n"
+    );
+}
+
+#[test]
+fn change_structure() {
+    let key = "k";
+    json! {
+        let entries = {key:["v"]}.unwrap();
+    }
+    let dict = Item::dict(entries);
+    let cell = path!({key}[0]Text).walk(dict).unwrap();
+    let Item::Text { value, .. } = cell.get() else {
+        unreachable!("this destructuring always succeeds because path walk did");
+    };
+    let b = String::from("b");
+    json! {
+        let patch = {"p": #[epilog = &b] (value)}.unwrap();
+    }
+    cell.set(Item::dict(patch));
+    assert_eq!(
+        File::try_from_dict_without_epilog(&dict)
+            .unwrap()
+            .to_string(),
+        "[k]\n\t{}\n\t\tp=v\n\t\t#b\n"
+    )
+}
+
+#[test]
+fn sample_list() {
+    json! {
+        let items = ["0","1","2","3","4","5","6","7","8","9"].unwrap();
+    }
+    arena! {
+        let mut arena = <8list>;
+    }
+    let picked = items.sample(arena.builder(), 4, 42).unwrap();
+    assert_eq!(picked, items.sample(arena.builder(), 4, 42).unwrap());
+    let positions = Vec::from_iter(
+        picked
+            .iter()
+            .map(|cell| items.iter().position(|c| *c == *cell).unwrap()),
+    );
+    assert_eq!(positions.len(), 4);
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(std::ptr::eq(
+        items.sample(arena.builder(), 10, 0).unwrap(),
+        items
+    ));
+}
+
+#[test]
+fn take_paths_from_list() {
+    json! {
+        let items = [{"a":"1","b":"2"},"x",{"c":["y","z"],"d":"w"}].unwrap();
+    }
+    arena! {
+        let mut arena = <4list,4dict>;
+    }
+    let kept = items
+        .take_paths(arena.builder(), &[path!([0]{"b"}Text), path!([2]{"c"}List)])
+        .unwrap();
+    let mut array = Entry::array::<1>();
+    array[0].get_mut().item = Item::list(kept);
+    let file = File {
+        cells: &array[..],
+        ..Default::default()
+    };
+    assert_eq!(
+        file.to_string(),
+        "[]\n\t{}\n\t\tb=2\n\t{}\n\t\t[c]\n\t\t\ty\n\t\t\tz\n"
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn push_cloned_from() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file =
+        arena.panic_first_error("[servers]\n\t{}\n\t\thost=a.example\n\t\tport=80\n\t\t#default\n");
+    let Item::List { cells: servers, .. } = file.cells[0].get().item else {
+        panic!()
+    };
+    let servers = servers
+        .push_cloned_from(
+            arena.builder(),
+            0,
+            &[(path!({"host"}Text), Item::text("b.example"))],
+        )
+        .unwrap();
+    let mut array = Entry::array::<1>();
+    array[0].get_mut().key = "servers".into();
+    array[0].get_mut().item = Item::list(servers);
+    let edited = File {
+        cells: &array[..],
+        ..Default::default()
+    };
+    assert_eq!(
+        edited.to_string(),
+        "[servers]\n\t{}\n\t\thost=a.example\n\t\tport=80\n\t\t#default\n\
+         \t{}\n\t\thost=b.example\n\t\tport=80\n\t\t#default\n"
+    );
+    let bad =
+        servers.push_cloned_from(arena.builder(), 0, &[(path!({"nope"}Text), Item::text(""))]);
+    assert_eq!(bad, Err("key not found"));
+    assert_eq!(
+        servers.push_cloned_from(arena.builder(), 9, &[]),
+        Err("no item at that index")
+    );
+}
+
+#[test]
+fn fill_defaults() {
+    json! {
+        let defaults = {"port":"80","tls":{"on":"no","cert":"x.pem"},"tags":["a"]}.unwrap();
+        let entries = {"tls":{"on":"yes"},"port":"8080"}.unwrap();
+    }
+    arena! {
+        let mut arena = <0list,6dict>;
+    }
+    let note = Comment::some("default");
+    let filled = entries
+        .fill_defaults(arena.builder(), defaults, note)
+        .unwrap();
+    let file = File {
+        cells: filled,
+        ..Default::default()
+    };
+    assert_eq!(
+        file.to_string(),
+        "{tls}\n\ton=yes\n\t//default\n\tcert=x.pem\nport=8080\n//default\n[tags]\n\ta\n"
+    );
+    let again = filled
+        .fill_defaults(arena.builder(), defaults, note)
+        .unwrap();
+    assert!(std::ptr::eq(again, filled));
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn translate_comments() {
+    use tindalwic::comments::{extract, inject};
+    let content = from_literal(
+        "
+        #
+            settings
+        //the server
+        {server}
+            #where to listen
+            port=80
+            #well known
+            [hosts]
+                a
+                #first
+    ",
+    );
+    arena! {
+        let mut arena = <1list,3dict>;
+    }
+    let mut file = arena.panic_first_error(&content);
+    let mut catalog = extract(&file);
+    assert_eq!(
+        Vec::from_iter(catalog.keys().map(ToString::to_string)),
+        vec![
+            "prolog",
+            "{server} prolog",
+            "{server} before",
+            "{server}{hosts}[0] epilog",
+            "{server}{port} epilog",
+        ]
+    );
+    catalog.retain(|key, _| key.to_string() != "{server} prolog");
+    for text in catalog.values_mut() {
+        *text = text.to_uppercase();
+    }
+    assert_eq!(inject(&mut file, &catalog), 4);
+    assert_eq!(
+        file.to_string(),
+        "#SETTINGS\n//THE SERVER\n{server}\n\t#where to listen\n\tport=80\n\t#WELL KNOWN\n\t[hosts]\n\t\ta\n\t\t#FIRST\n"
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn catalog_keys_are_steps() {
+    use tindalwic::comments::{CatalogStep, Slot, extract, inject};
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let mut file = arena.panic_first_error("//one\na}{b=1\n{a}\n\t//two\n\tb=2\n");
+    let mut catalog = extract(&file);
+    let keys = Vec::from_iter(catalog.keys().map(|key| key.steps.clone()));
+    assert_eq!(
+        keys,
+        [
+            vec![
+                CatalogStep::Entry("a".into()),
+                CatalogStep::Entry("b".into())
+            ],
+            vec![CatalogStep::Entry("a}{b".into())],
+        ]
+    );
+    assert!(catalog.keys().all(|key| key.slot == Slot::Before));
+    for text in catalog.values_mut() {
+        *text = text.to_uppercase();
+    }
+    assert_eq!(inject(&mut file, &catalog), 2);
+    assert_eq!(file.to_string(), "//ONE\na}{b=1\n{a}\n\t//TWO\n\tb=2\n");
+}
+
+#[test]
+fn faker_replaces_consistently() {
+    json! {
+        let entries = {"user_name":"Jane Roe","mail":"jane@corp.example","hosts":["10.1.2.3","jane@corp.example","keep"]}.unwrap();
+    }
+    let file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    assert_eq!(tindalwic::faker::Faker::new(7).file(&file), 4);
+    let Item::Text { value: name, .. } = entries[0].get().item else {
+        panic!()
+    };
+    let Item::Text { value: mail, .. } = entries[1].get().item else {
+        panic!()
+    };
+    let Item::List { cells: hosts, .. } = entries[2].get().item else {
+        panic!()
+    };
+    assert_ne!(name, Value::from("Jane Roe"));
+    assert!(mail.only_line().unwrap().contains("@example."));
+    assert_eq!(hosts[1].get(), Item::text(mail.only_line().unwrap()));
+    assert_ne!(hosts[0].get(), Item::text("10.1.2.3"));
+    assert_eq!(hosts[2].get(), Item::text("keep"));
+}
+
+#[test]
+fn opaque() {
+    json! {
+        let entries = {"user_name":"!enc:v1:c2VjcmV0","hosts":["!enc:v1:MTAuMS4yLjM=","!enc"]}.unwrap();
+    }
+    let file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    let before = file.to_string();
+    assert!(entries[0].get().item.is_opaque());
+    let Item::List { cells: hosts, .. } = entries[1].get().item else {
+        panic!()
+    };
+    assert!(hosts[0].get().is_opaque());
+    assert!(!hosts[1].get().is_opaque());
+    assert!(!entries[1].get().item.is_opaque());
+    assert_eq!(tindalwic::faker::Faker::new(7).file(&file), 0);
+    assert_eq!(file.to_string(), before);
+}
+
+#[test]
+fn aliases() {
+    json! {
+        let entries = {"port":"80","old_port":"!alias:port","older":"!alias:old_port","gone":"!alias:nope","loop":"!alias:loop"}.unwrap();
+    }
+    let resolved = entries.resolve("older".into()).unwrap();
+    assert_eq!(resolved.get().key, Value::from("port"));
+    assert!(std::ptr::eq(
+        resolved,
+        entries.resolve("port".into()).unwrap()
+    ));
+    assert_eq!(entries.resolve("gone".into()), None);
+    assert_eq!(entries.resolve("loop".into()), None);
+    let chain: Vec<Value> = entries.alias_chain("older".into()).collect();
+    assert_eq!(chain, ["older".into(), "old_port".into(), "port".into()]);
+    assert_eq!(entries.alias_chain("loop".into()).count(), 6);
+    let file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    assert!(file.to_string().contains("old_port=!alias:port\n"));
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn dict_mutation() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("a=1\n\n//about b\nb=2\nc=3\n");
+    let arena = arena.builder();
+    let new = Entry {
+        before: Comment::some("new"),
+        key: "x".into(),
+        item: Item::text("9"),
+        ..Entry::default()
+    };
+    let cells = file.cells.insert(arena, 1, new).unwrap();
+    let encode = |cells| {
+        File {
+            cells,
+            ..File::default()
+        }
+        .to_string()
+    };
+    assert_eq!(encode(cells), "a=1\n//new\nx=9\n\n//about b\nb=2\nc=3\n");
+    assert_eq!(
+        file.cells.insert(arena, 1, cells[0].get()),
+        Err("duplicate key")
+    );
+    assert_eq!(file.cells.insert(arena, 4, new), Err("index out of bounds"));
+    let (cells, removed) = cells.remove(arena, "b".into()).unwrap();
+    let removed = removed.unwrap();
+    assert!(removed.gap);
+    assert_eq!(removed.before, Comment::some("about b"));
+    assert_eq!(encode(cells), "a=1\n//new\nx=9\nc=3\n");
+    let (same, removed) = cells.remove(arena, "b".into()).unwrap();
+    assert!(std::ptr::eq(same, cells) && removed.is_none());
+    file.cells.rename("b".into(), "bee".into()).unwrap();
+    assert_eq!(encode(file.cells), "a=1\n\n//about b\nbee=2\nc=3\n");
+    assert_eq!(
+        file.cells.rename("c".into(), "a".into()),
+        Err("duplicate key")
+    );
+    assert_eq!(
+        file.cells.rename("b".into(), "c".into()),
+        Err("key not found")
+    );
+    file.cells.rename("c".into(), "c".into()).unwrap();
+}
+
+#[test]
+fn dict_iteration() {
+    json! {
+        let entries = {"a":"1","b":["x"],"c":{}}.unwrap();
+    }
+    let keys: Vec<Value> = entries.keys().collect();
+    assert_eq!(keys, ["a".into(), "b".into(), "c".into()]);
+    let kinds: Vec<bool> = entries
+        .items()
+        .map(|item| matches!(item, Item::Text { .. }))
+        .collect();
+    assert_eq!(kinds, [true, false, false]);
+    let (key, item) = entries.pairs().next().unwrap();
+    assert_eq!((key, item), ("a".into(), Item::text("1")));
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn collecting() {
+    use tindalwic::cells::{collect_entries, collect_items};
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("//first\na=1\n");
+    let arena = arena.builder();
+    let items = collect_items(arena, ["x", "y"].map(Item::text)).unwrap();
+    let pairs = [
+        ("b".into(), Item::text("2")),
+        ("c".into(), Item::list(items)),
+    ];
+    let cells = file.cells.extended(arena, pairs).unwrap();
+    let encode = |cells| {
+        File {
+            cells,
+            ..File::default()
+        }
+        .to_string()
+    };
+    assert_eq!(encode(cells), "//first\na=1\nb=2\n[c]\n\tx\n\ty\n");
+    let copy = collect_entries(arena, cells.pairs()).unwrap();
+    assert_eq!(encode(copy), "a=1\nb=2\n[c]\n\tx\n\ty\n");
+    let twice = cells.pairs().chain(cells.pairs().take(1));
+    assert_eq!(collect_entries(arena, twice), Err("duplicate key"));
+    assert!(collect_items(arena, []).unwrap().is_empty());
+}
+
+#[test]
+fn by_key_and_index() {
+    json! {
+        let entries = {"servers":[{"port":"80"},{"port":"443"}]}.unwrap();
+    }
+    let item = Item::dict(entries);
+    let port = || item.by_key("servers")?.by_index(1)?.by_key("port");
+    assert_eq!(port(), Some(Item::text("443")));
+    assert_eq!(item.by_key("missing"), None);
+    assert_eq!(item.by_index(0), None); // not a list
+    assert_eq!(item.by_key("servers").unwrap().by_index(2), None);
+    let file = File {
+        cells: entries,
+        ..File::default()
+    };
+    let port = file
+        .by_key("servers")
+        .and_then(|servers| servers.by_index(0))
+        .and_then(|server| server.by_key("port"));
+    assert_eq!(port, Some(Item::text("80")));
+    assert_eq!(file.by_key("port"), None);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn list_editing() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("[l]\n\ta\n\t#about a\n\tb\n\tc\n");
+    let arena = arena.builder();
+    let Item::List { cells, .. } = file.cells[0].get().item else {
+        panic!()
+    };
+    let encode = |cells| Item::list(cells).encode_fragment(0);
+    let cells = cells.insert(arena, 1, Item::text("new")).unwrap();
+    assert_eq!(encode(cells), "[]\n\ta\n\t#about a\n\tnew\n\tb\n\tc\n");
+    let (cells, removed) = cells.remove(arena, 0).unwrap();
+    assert!(matches!(
+        removed,
+        Item::Text {
+            epilog: Some(_),
+            ..
+        }
+    ));
+    assert_eq!(encode(cells), "[]\n\tnew\n\tb\n\tc\n");
+    cells.swap_at(0, 2).unwrap();
+    assert_eq!(encode(cells), "[]\n\tc\n\tb\n\tnew\n");
+    assert_eq!(encode(cells.truncate(1)), "[]\n\tc\n");
+    assert_eq!(cells.truncate(9).len(), 3);
+    let cells = cells.splice(arena, 1..3, [removed]).unwrap();
+    assert_eq!(encode(cells), "[]\n\tc\n\ta\n\t#about a\n");
+    assert!(cells.remove(arena, 2).is_err());
+    assert!(cells.swap_at(0, 2).is_err());
+    assert!(cells.splice(arena, 1..3, []).is_err());
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn dict_reordering() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("c=3\n\n//about a\na=1\nd=4\nb=2\n");
+    let cells = file.cells;
+    cells.move_key_before("b".into(), "a".into()).unwrap();
+    assert_eq!(file.to_string(), "c=3\nb=2\n\n//about a\na=1\nd=4\n");
+    cells.move_key_before("c".into(), "d".into()).unwrap();
+    assert_eq!(file.to_string(), "b=2\n\n//about a\na=1\nc=3\nd=4\n");
+    cells.move_key_to("d".into(), 0).unwrap();
+    assert_eq!(file.to_string(), "d=4\nb=2\n\n//about a\na=1\nc=3\n");
+    cells.sort_by_key_name();
+    assert_eq!(file.to_string(), "\n//about a\na=1\nb=2\nc=3\nd=4\n");
+    cells.sort_entries_by(|a, b| b.key.lines().cmp(a.key.lines()));
+    assert_eq!(file.to_string(), "d=4\nc=3\nb=2\n\n//about a\na=1\n");
+    assert_eq!(cells.move_key_to("x".into(), 0), Err("key not found"));
+    assert_eq!(cells.move_key_to("a".into(), 4), Err("index out of bounds"));
+    assert_eq!(
+        cells.move_key_before("a".into(), "x".into()),
+        Err("key not found")
+    );
+}
+
+#[test]
+fn item_accessors() {
+    json! {
+        let entries = {"t":"x","l":["y"],"d":{"k":"z"}}.unwrap();
+    }
+    let [t, l, d] = [0, 1, 2].map(|at| entries[at].get().item);
+    assert!(t.is_text() && !t.is_list() && !t.is_dict());
+    assert!(l.is_list() && !l.is_text());
+    assert!(d.is_dict() && !d.is_list());
+    assert_eq!(t.as_text(), Some("x".into()));
+    assert_eq!(l.as_list().unwrap().len(), 1);
+    assert_eq!(d.as_dict().unwrap()[0].get().key, Value::from("k"));
+    assert_eq!((t.as_list(), t.as_dict(), l.as_text()), (None, None, None));
+    let mut entry = entries[0].get();
+    *entry.item.as_text_mut().unwrap() = "changed".into();
+    entries[0].set(entry);
+    assert_eq!(entries[0].get().item, Item::text("changed"));
+    let mut entry = entries[2].get();
+    *entry.item.as_dict_mut().unwrap() = &[];
+    assert!(entry.item.as_list_mut().is_none());
+    entries[2].set(entry);
+    assert!(entries[2].get().item.as_dict().unwrap().is_empty());
+}
+
+#[test]
+fn scalar_getters() {
+    use tindalwic::number::ScalarError;
+    json! {
+        let entries = {"on":"true","n":"-42","f":"2.5","two":"1\n2","list":[]}.unwrap();
+    }
+    let [on, n, f, two, list] = [0, 1, 2, 3, 4].map(|at| entries[at].get().item);
+    assert_eq!(on.as_bool(), Ok(true));
+    assert_eq!(n.as_i64(), Ok(-42));
+    assert_eq!(n.as_f64(), Ok(-42.0));
+    assert_eq!(f.as_f64(), Ok(2.5));
+    assert_eq!(n.as_u64(), Err(ScalarError::Spelling("u64")));
+    assert_eq!(f.as_i64(), Err(ScalarError::Spelling("i64")));
+    assert_eq!(
+        Value::from("yes").as_bool(),
+        Err(ScalarError::Spelling("bool"))
+    );
+    assert_eq!(two.as_i64(), Err(ScalarError::Lines));
+    assert_eq!(list.as_bool(), Err(ScalarError::NotText));
+    assert_eq!(Value::from("7").as_u64(), Ok(7));
+    assert_eq!(ScalarError::Spelling("u64").to_string(), "not a u64");
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn typed_setters() {
+    use tindalwic::number::Format;
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("port=80\n#the port\n[list]\n");
+    let arena = arena.builder();
+    let mut entry = file.cells[0].get();
+    entry.item.set_i64(arena, 8080).unwrap();
+    file.cells[0].set(entry);
+    assert_eq!(file.to_string(), "port=8080\n#the port\n[list]\n");
+    entry.item.set_f64(arena, 0.5, Format::default()).unwrap();
+    file.cells[0].set(entry);
+    assert_eq!(file.to_string(), "port=0.5\n#the port\n[list]\n");
+    entry.item.set_bool(true).unwrap();
+    file.cells[0].set(entry);
+    assert_eq!(file.to_string(), "port=true\n#the port\n[list]\n");
+    entry.item.set_display(arena, 'x').unwrap();
+    file.cells[0].set(entry);
+    assert_eq!(file.to_string(), "port=x\n#the port\n[list]\n");
+    let mut list = file.cells[1].get().item;
+    assert_eq!(list.set_bool(false), Err("not a text"));
+    assert_eq!(list.set_i64(arena, 1), Err("not a text"));
+    let mut text = Item::text("");
+    assert_eq!(
+        text.set_display(arena, "x".repeat(600)),
+        Err("text too long")
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn semantic_eq() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let plain = arena.panic_first_error("a=1\n{b}\n\t<c>\n\t\tx\n\t\ty\n[d]\n\tz\n");
+    let noted = "#!run\n#intro\n\n//a\na=1\n#1\n{b}\n\t#b\n\t<c>\n\t\tx\n\t\ty\n[d]\n\tz\n\t#z\n";
+    let noted = arena.panic_first_error(noted);
+    assert!(plain.semantic_eq(&noted) && noted.semantic_eq(&plain));
+    assert_ne!(plain, noted);
+    for other in [
+        "a=1\n{b}\n\tc=x\n[d]\n\tz\n",
+        "{b}\n\t<c>\n\t\tx\n\t\ty\na=1\n[d]\n\tz\n",
+    ] {
+        assert!(!plain.semantic_eq(&arena.panic_first_error(other)));
+    }
+    let list = Item::list(&[]);
+    assert!(list.semantic_eq(&list) && !list.semantic_eq(&Item::dict(&[])));
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+// the cells are not changed while the files and items are keys
+#[allow(clippy::mutable_key_type)]
+fn hashing() {
+    use std::collections::HashSet;
+    use std::hash::{BuildHasher, RandomState};
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let plain = arena.panic_first_error("a=1\n[b]\n\tx\n");
+    let noted = arena.panic_first_error("//a\na=1\n\n[b]\n\tx\n\t#x\n");
+    let again = arena.panic_first_error("a=1\n[b]\n\tx\n");
+    let files: HashSet<File> = [plain, noted, again].into_iter().collect();
+    assert_eq!(files.len(), 2);
+    assert!(files.contains(&plain) && files.contains(&noted));
+    let items: HashSet<Item> = plain.cells.iter().map(|cell| cell.get().item).collect();
+    assert_eq!(items.len(), 2);
+    assert!(items.contains(&Item::text("1")));
+    let state = RandomState::new();
+    let semantic = |file: &File| {
+        let mut hasher = state.build_hasher();
+        file.semantic_hash(&mut hasher);
+        std::hash::Hasher::finish(&hasher)
+    };
+    assert_eq!(semantic(&plain), semantic(&noted));
+    assert_ne!(state.hash_one(plain), state.hash_one(noted));
+    let other = arena.panic_first_error("a=1\n[b]\n\ty\n");
+    assert_ne!(semantic(&plain), semantic(&other));
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn set_text() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("greeting=hi\n#said\n[list]\n");
+    let mut entry = file.cells[0].get();
+    {
+        let temporary = format!("hello\n{}", "world");
+        entry.item.set_text(arena.builder(), &temporary).unwrap();
+    } // gone, but the copy is in the arena
+    file.cells[0].set(entry);
+    assert_eq!(
+        file.to_string(),
+        "<greeting>\n\thello\n\tworld\n#said\n[list]\n"
+    );
+    let mut list = file.cells[1].get().item;
+    assert_eq!(list.set_text(arena.builder(), "x"), Err("not a text"));
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn line_editing() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("{d}\n\t<script>\n\t\tone\n\t\tthree\n\t#run it\n");
+    let Item::Dict { cells, .. } = file.cells[0].get().item else {
+        panic!()
+    };
+    let mut entry = cells[0].get();
+    let item = &mut entry.item;
+    item.insert_line(arena.builder(), 1, "two").unwrap();
+    item.replace_line(arena.builder(), 2, "3").unwrap();
+    item.insert_line(arena.builder(), 3, "four").unwrap();
+    item.remove_line(arena.builder(), 0).unwrap();
+    cells[0].set(entry);
+    let expected = "{d}\n\t<script>\n\t\ttwo\n\t\t3\n\t\tfour\n\t#run it\n";
+    assert_eq!(file.to_string(), expected);
+    let item = &mut entry.item;
+    assert_eq!(
+        item.remove_line(arena.builder(), 3),
+        Err("no line at that index")
+    );
+    assert_eq!(
+        item.replace_line(arena.builder(), 3, "x"),
+        Err("no line at that index")
+    );
+    assert_eq!(
+        item.insert_line(arena.builder(), 4, "x"),
+        Err("no line at that index")
+    );
+    let mut empty = Item::text("");
+    empty.insert_line(arena.builder(), 0, "only").unwrap();
+    assert_eq!(empty, Item::text("only"));
+    empty.remove_line(arena.builder(), 0).unwrap();
+    assert_eq!(empty, Item::text(""));
+    let mut list = Item::list(&[]);
+    assert_eq!(list.insert_line(arena.builder(), 0, "x"), Err("not a text"));
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn push_lines() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("log=\n");
+    let mut entry = file.cells[0].get();
+    entry.item.push_line(arena.builder(), "started").unwrap();
+    file.cells[0].set(entry);
+    assert_eq!(file.to_string(), "log=started\n");
+    let more = [String::from("working"), String::from("done")];
+    let lines = more.iter().map(String::as_str);
+    entry.item.extend_lines(arena.builder(), lines).unwrap();
+    file.cells[0].set(entry);
+    assert_eq!(file.to_string(), "<log>\n\tstarted\n\tworking\n\tdone\n");
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn get_or_insert_with() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("{server}\n\tport=80\n");
+    let arena = arena.builder();
+    let (cells, server) = file
+        .cells
+        .get_or_insert_with(arena, "server".into(), || panic!("not needed"))
+        .unwrap();
+    assert!(std::ptr::eq(cells, file.cells));
+    assert_eq!(server.get().key, Value::from("server"));
+    let (cells, logging) = cells
+        .get_or_insert_with(arena, "logging".into(), || Item::dict(&[]))
+        .unwrap();
+    let mut entry = logging.get();
+    let Item::Dict { cells: inner, .. } = entry.item else {
+        panic!()
+    };
+    let (inner, _) = inner
+        .get_or_insert_with(arena, "level".into(), || Item::text("info"))
+        .unwrap();
+    entry.item = Item::dict(inner);
+    logging.set(entry);
+    let file = File { cells, ..file };
+    assert_eq!(
+        file.to_string(),
+        "{server}\n\tport=80\n{logging}\n\tlevel=info\n"
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn dict_retain_drain() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("a=1\nlegacy_b=2\n//c\nc=3\nlegacy_d=4\n");
+    let arena = arena.builder();
+    let encode = |cells| {
+        File {
+            cells,
+            ..File::default()
+        }
+        .to_string()
+    };
+    let legacy = |entry: &Entry| entry.key.only_line().unwrap().starts_with("legacy_");
+    let kept = file.cells.retain(arena, |entry| !legacy(entry)).unwrap();
+    assert_eq!(encode(kept), "a=1\n//c\nc=3\n");
+    let (rest, removed) = file.cells.drain(arena, 1..3).unwrap();
+    assert_eq!(encode(rest), "a=1\nlegacy_d=4\n");
+    assert_eq!(encode(removed), "legacy_b=2\n//c\nc=3\n");
+    assert!(file.cells.drain(arena, 3..5).is_err());
+    assert!(file.cells.retain(arena, |_| false).unwrap().is_empty());
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn list_retain_drain() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("[l]\n\tkeep\n\tdrop\n\t#dropped\n\tkeep too\n");
+    let arena = arena.builder();
+    let cells = file.cells[0].get().item.as_list().unwrap();
+    let encode = |cells| Item::list(cells).encode_fragment(0);
+    let kept = cells
+        .retain(arena, |item| item.as_text() != Some("drop".into()))
+        .unwrap();
+    assert_eq!(encode(kept), "[]\n\tkeep\n\tkeep too\n");
+    let (rest, removed) = cells.drain(arena, 1..2).unwrap();
+    assert_eq!(encode(rest), "[]\n\tkeep\n\tkeep too\n");
+    assert_eq!(encode(removed), "[]\n\tdrop\n\t#dropped\n");
+    let moved = rest.insert(arena, 0, removed[0].get()).unwrap();
+    assert_eq!(
+        encode(moved),
+        "[]\n\tdrop\n\t#dropped\n\tkeep\n\tkeep too\n"
+    );
+    assert!(cells.drain(arena, 2..4).is_err());
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn merge_from() {
+    use tindalwic::cells::MergeDepth;
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let defaults = "//the name\nname=app\n{server}\n\t#defaults\n\tport=80\n\thost=localhost\n";
+    let defaults = arena.panic_first_error(defaults);
+    let overrides = "{server}\n\tport=8080\n\n//added\nextra=yes\n";
+    let overrides = arena.panic_first_error(overrides);
+    let arena = arena.builder();
+    let encode = |cells| {
+        File {
+            cells,
+            ..File::default()
+        }
+        .to_string()
+    };
+    let deep = defaults
+        .cells
+        .merge_from(arena, overrides.cells, MergeDepth::Deep);
+    assert_eq!(
+        encode(deep.unwrap()),
+        "//the name\nname=app\n{server}\n\t#defaults\n\tport=8080\n\thost=localhost\n\n//added\nextra=yes\n"
+    );
+    let shallow = defaults
+        .cells
+        .merge_from(arena, overrides.cells, MergeDepth::Shallow);
+    assert_eq!(
+        encode(shallow.unwrap()),
+        "//the name\nname=app\n{server}\n\tport=8080\n\n//added\nextra=yes\n"
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn merge_with_comments() {
+    use tindalwic::cells::{CommentMergePolicy, MergeDepth};
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let ours = arena.panic_first_error("//port for tests\nport=80\n{server}\n\t#ours\n\thost=a\n");
+    let theirs = arena.panic_first_error("//port in production\nport=8080\n{server}\n\thost=b\n");
+    let arena = arena.builder();
+    let mut merged = |policy| {
+        let cells = ours
+            .cells
+            .merge_with_comments(arena, theirs.cells, MergeDepth::Deep, policy)
+            .unwrap();
+        File {
+            cells,
+            ..File::default()
+        }
+        .to_string()
+    };
+    assert_eq!(
+        merged(CommentMergePolicy::KeepOurs),
+        "//port for tests\nport=8080\n{server}\n\t#ours\n\thost=b\n"
+    );
+    assert_eq!(
+        merged(CommentMergePolicy::KeepTheirs),
+        "//port in production\nport=8080\n{server}\n\t#ours\n\thost=b\n"
+    );
+    assert_eq!(
+        merged(CommentMergePolicy::Concatenate("\n")),
+        "//port for tests\n\tport in production\nport=8080\n{server}\n\t#ours\n\thost=b\n"
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn arena_capacity() {
+    use tindalwic::cells::collect_entries;
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::with_capacity(1 << 16);
+    let mut arena = tindalwic::bumpalo::Arena::with_capacity(&bump, 0, 1000);
+    arena.reserve(10, 0);
+    let keys: Vec<String> = (0..1000).map(|n| format!("k{n}")).collect();
+    let pairs = keys
+        .iter()
+        .map(|key| (key.as_str().into(), Item::text("v")));
+    let cells = collect_entries(arena.builder(), pairs).unwrap();
+    assert_eq!(cells.len(), 1000);
+    assert!(!cells.is_empty());
+}
+
+#[test]
+fn entry_builder() {
+    use std::cell::Cell;
+    let inner = [Cell::new(Entry::text("port", "80"))];
+    let items = [Cell::new(Item::text("x"))];
+    let cells = [
+        Cell::new(Entry::text("name", "app").with_before("who")),
+        Cell::new(Entry::dict("server", &inner).with_gap()),
+        Cell::new(Entry::list("tags", &items).with_gap().with_before("labels")),
+    ];
+    let file = File {
+        cells: &cells,
+        ..File::default()
+    };
+    let expected = "//who\nname=app\n\n{server}\n\tport=80\n\n//labels\n[tags]\n\tx\n";
+    assert_eq!(file.to_string(), expected);
+}
+
+#[test]
+fn item_from() {
+    use std::cell::Cell;
+    let items = [Cell::new(Item::from("x"))];
+    let entries = [Cell::new(Entry::new("k", Value::from("v").into()))];
+    assert_eq!(Item::from("x"), Item::text("x"));
+    assert_eq!(items[0].get(), Item::from(Value::from("x")));
+    assert_eq!(Item::from(&items[..]), Item::list(&items));
+    assert_eq!(Item::from(&entries[..]), Item::dict(&entries));
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn json_doc_comments() {
+    let port = Item::text("80");
+    json! {
+        let entries = {
+            /// where to listen
+            "host": "localhost",
+            /// two
+            /// lines
+            "ports": ["8080"],
+            "plain": {
+                /// nested
+                "port": (port),
+            },
+        }.unwrap();
+    }
+    let file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    let expect = "
+        //where to listen
+        host=localhost
+        //two
+            lines
+        [ports]
+            8080
+        {plain}
+            //nested
+            port=80
+    ";
+    assert_eq!(file.to_string(), from_literal(expect));
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn json_comment_attributes() {
+    let said = String::from("said");
+    json! {
+        let entries = {
+            #[prolog = "the server"]
+            #[epilog = "end of server"]
+            "server": {
+                "hosts": [
+                    #[epilog = "the main one"]
+                    "a",
+                    #[prolog = "more"]
+                    ["b"],
+                ],
+            },
+            "name": #[epilog = &said] "app",
+        }.unwrap();
+    }
+    let file = File {
+        cells: entries,
+        ..Default::default()
+    };
+    let expect = "
+        {server}
+            #the server
+            [hosts]
+                a
+                #the main one
+                []
+                    #more
+                    b
+        #end of server
+        name=app
+        #said
+    ";
+    assert_eq!(file.to_string(), from_literal(expect));
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn file_entry_api() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let mut file = File::parse(&mut arena, "#!run\n#about\na=1\nb=2\n").unwrap();
+    let arena = arena.builder();
+    assert_eq!(file.hashbang(), Some("run".into()));
+    assert_eq!(file.prolog(), Some("about".into()));
+    assert_eq!(file.len(), 2);
+    assert_eq!(file.get("b").unwrap().item, Item::text("2"));
+    assert!(file.get("c").is_none());
+    file.insert(arena, 1, Entry::text("c", "3")).unwrap();
+    assert_eq!(
+        file.insert(arena, 0, Entry::text("a", "0")),
+        Err("duplicate key")
+    );
+    let removed = file.remove(arena, "a").unwrap().unwrap();
+    assert_eq!(removed.item, Item::text("1"));
+    assert_eq!(file.remove(arena, "a").unwrap(), None);
+    let keys: Vec<_> = file.iter().map(|entry| entry.key).collect();
+    assert_eq!(keys, vec![Value::from("c"), Value::from("b")]);
+    assert_eq!(file.to_string(), "#!run\n#about\nc=3\nb=2\n");
+    assert!(!file.is_empty());
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn key_index() {
+    use tindalwic::alloc::KeyIndex;
+    let keys: Vec<String> = (0..100).map(|n| format!("key{n}")).collect();
+    let entries: Vec<_> = keys
+        .iter()
+        .map(|key| std::cell::Cell::new(Entry::text(key, "v")))
+        .collect();
+    let index = KeyIndex::new(&entries);
+    for (at, key) in keys.iter().enumerate() {
+        assert_eq!(index.position(key.as_str().into()), Some(at));
+    }
+    assert_eq!(index.position("missing".into()), None);
+    entries[7].set(Entry::text("renamed", "v"));
+    assert!(index.get("key7").is_none());
+    assert!(index.get("renamed").is_none());
+    assert!(KeyIndex::new(&[]).get("").is_none());
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn walk_in_document_order() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("a=1\n[b]\n\tx\n\t{}\n\t\tc=2\n");
+    let mut seen = Vec::new();
+    file.walk(&mut |trail, item| seen.push(format!("{trail} {}", item.is_text())));
+    let expect = [
+        "{a} true",
+        "{b} false",
+        "{b}[0] true",
+        "{b}[1] false",
+        "{b}[1]{c} true",
+    ];
+    assert_eq!(seen, expect);
+    let mut texts = 0;
+    file.walk_mut(&mut |_, item| {
+        if item.is_text() {
+            texts += 1;
+            *item = Item::text("0");
+        }
+    });
+    assert_eq!(texts, 3);
+    assert_eq!(file.to_string(), "a=0\n[b]\n\t0\n\t{}\n\t\tc=0\n");
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn visitor_hooks() {
+    use tindalwic::comments::Slot;
+    use tindalwic::walk::{Trail, Visitor};
+    struct Log(Vec<String>);
+    impl<'a> Visitor<'a> for Log {
+        fn visit_text(&mut self, trail: Option<&Trail<'_, 'a>>, value: Value<'a>) {
+            self.0.push(format!("{} {value}", trail.unwrap()));
+        }
+        fn enter_list(&mut self, _: Option<&Trail<'_, 'a>>, len: usize) {
+            self.0.push(format!("[{len}"));
+        }
+        fn exit_list(&mut self, _: Option<&Trail<'_, 'a>>) {
+            self.0.push("]".into());
+        }
+        fn enter_dict(&mut self, _: Option<&Trail<'_, 'a>>, len: usize) {
+            self.0.push(format!("{{{len}"));
+        }
+        fn exit_dict(&mut self, _: Option<&Trail<'_, 'a>>) {
+            self.0.push("}".into());
+        }
+        fn visit_comment(&mut self, _: Option<&Trail<'_, 'a>>, slot: Slot, comment: Comment<'a>) {
+            self.0.push(format!("{slot}:{}", comment.value));
+        }
+    }
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("#!bang\n//b\na=1\n[l]\n\tx\n\t#e\n");
+    let mut log = Log(Vec::new());
+    file.accept(&mut log);
+    let expect = [
+        "hashbang:bang",
+        "{2",
+        "before:b",
+        "{a} 1",
+        "[1",
+        "{l}[0] x",
+        "epilog:e",
+        "]",
+        "}",
+    ];
+    assert_eq!(log.0, expect);
+    let mut log = Log(Vec::new());
+    Item::list(&[]).accept(&mut log);
+    assert_eq!(log.0, ["[0", "]"]);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn walk_entries_mut() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("{env}\n\thome=h\n\t[paths]\n\t\t{}\n\t\t\tbin=b\nuser=u\n");
+    let arena = arena.builder();
+    file.walk_entries_mut(&mut |trail, entry| {
+        // the trail has the new key of each entry it passes through
+        if trail.to_string().to_lowercase().starts_with("{env}") {
+            let upper = entry.key.to_string().to_uppercase();
+            entry.key = arena.intern(&upper).unwrap().into();
+        }
+    });
+    let expect = "{ENV}\n\tHOME=h\n\t[PATHS]\n\t\t{}\n\t\t\tBIN=b\nuser=u\n";
+    assert_eq!(file.to_string(), expect);
+    let list = file.by_key("ENV").unwrap().by_key("PATHS").unwrap();
+    let mut count = 0;
+    list.walk_mut(&mut |trail, _| {
+        assert_eq!(trail.to_string(), ["[0]", "[0]{BIN}"][count]);
+        count += 1;
+    });
+    assert_eq!(count, 2);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn cursor_moves() {
+    use tindalwic::cursor::Cursor;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("a=1\n[b]\n\tx\n\ty\n{c}\n\td=2\n");
+    let mut cursor = Cursor::new(&file);
+    assert!(cursor.item().is_none());
+    assert!(!cursor.up());
+    assert!(cursor.down_key("b"));
+    assert!(cursor.down_index(1));
+    assert_eq!(cursor.item(), Some(Item::text("y")));
+    assert!(!cursor.next_sibling());
+    assert!(cursor.prev_sibling());
+    assert!(cursor.key().is_none());
+    cursor.set_item(Item::text("z")).unwrap();
+    assert!(!cursor.down_index(0));
+    assert_eq!(cursor.depth(), 2);
+    assert!(cursor.up());
+    assert!(cursor.next_sibling());
+    assert_eq!(cursor.key(), Some("c".into()));
+    assert!(cursor.down_index(0));
+    let branches: Vec<_> = cursor.branches().map(|branch| branch.to_string()).collect();
+    assert_eq!(branches, ["{c}", "{d}"]);
+    assert!(!cursor.down_key("missing"));
+    assert!(cursor.up() && cursor.up() && !cursor.up());
+    assert_eq!(
+        cursor.set_item(Item::text("")),
+        Err("cursor is at the root")
+    );
+    assert_eq!(file.to_string(), "a=1\n[b]\n\tz\n\ty\n{c}\n\td=2\n");
+}
+
+#[test]
+fn item_take_replace() {
+    json! {
+        let entries = {"a":"1","b":["x"]}.unwrap();
+    }
+    let mut entry = entries[1].get();
+    let list = entry.item.take();
+    assert_eq!(entry.item, Item::default());
+    assert_eq!(entry.item.replace(list), Item::text(""));
+    entries[1].set(entry);
+    let mut first = entries[0].get();
+    let old = first.item.replace(Item::text("2"));
+    assert_eq!(old, Item::text("1"));
+    assert_eq!(first.item.as_text(), Some("2".into()));
+    assert!(entries[1].get().item.is_list());
+}
+
+#[test]
+#[cfg(all(feature = "std", feature = "bumpalo"))]
+fn detach_subtree() {
+    let document = {
+        let source = String::from("{server}\n\t#the server\n\tport=80\n\t[hosts]\n\t\ta\n");
+        let bump = bumpalo::Bump::new();
+        let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+        let file = arena.panic_first_error(&source);
+        assert!(
+            file.by_key("server")
+                .unwrap()
+                .by_key("port")
+                .unwrap()
+                .detach()
+                .is_none()
+        );
+        file.by_key("server").unwrap().detach().unwrap()
+    };
+    assert_eq!(document.as_str(), "#the server\nport=80\n[hosts]\n\ta\n");
+    assert_eq!(document.get_str(&["port"]), Some("80".into()));
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn item_comment_accessors() {
+    let mut text = Item::text("t");
+    assert_eq!(text.prolog(), None);
+    assert_eq!(
+        text.set_prolog(Comment::some("p")),
+        Err("a text has no prolog")
+    );
+    assert_eq!(text.set_prolog(None), Ok(()));
+    text.set_epilog(Comment::some("e"));
+    assert_eq!(text.epilog(), Comment::some("e"));
+    assert_eq!(text.to_string(), "t\n#e\n");
+    let mut list = Item::list(&[]);
+    list.set_prolog(Comment::some("p")).unwrap();
+    assert_eq!(list.prolog(), Comment::some("p"));
+    assert_eq!(list.epilog(), None);
+    list.set_epilog(Comment::some("e"));
+    list.set_prolog(None).unwrap();
+    assert_eq!(list.to_string(), "[]\n#e\n");
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn edit_comment_text() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("//teh port\nport=80\n");
+    let arena = arena.builder();
+    let mut entry = file.cells[0].get();
+    let mut before = entry.before.unwrap();
+    before.set_text(arena, &String::from("the port")).unwrap();
+    before.push_line(arena, "(see docs)").unwrap();
+    entry.before = Some(before);
+    file.cells[0].set(entry);
+    assert_eq!(file.to_string(), "//the port\n\t(see docs)\nport=80\n");
+    let mut empty = Comment::interned(arena, "").unwrap();
+    empty.push_line(arena, "x").unwrap();
+    assert_eq!(empty.value.lines().collect::<Vec<_>>(), ["", "x"]);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn comment_from_lines() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let arena = arena.builder();
+    let lines = [String::from("one"), String::from("two")];
+    let comment = Comment::from_lines(arena, lines.iter().map(String::as_str)).unwrap();
+    let entry = Entry {
+        before: Some(comment),
+        ..Entry::text("k", "v")
+    };
+    assert_eq!(entry.to_string(), "//one\n\ttwo\nk=v\n");
+    let empty = Comment::from_lines(arena, []).unwrap();
+    assert_eq!(empty.value, "".into());
+    let who = "ops";
+    let comment = Comment::from_fmt(arena, format_args!("edited by {who}\non {}", 3)).unwrap();
+    assert_eq!(
+        comment.value.lines().collect::<Vec<_>>(),
+        ["edited by ops", "on 3"]
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn comment_append_line() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("a=1\n");
+    let arena = arena.builder();
+    let mut entry = file.cells[0].get();
+    for day in 1..3 {
+        let line = format!("edited on day {day}");
+        Comment::append_line(&mut entry.before, arena, &line).unwrap();
+    }
+    file.cells[0].set(entry);
+    let encoded = file.to_string();
+    assert_eq!(encoded, "//edited on day 1\n\tedited on day 2\na=1\n");
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    assert_eq!(arena.panic_first_error(&encoded).to_string(), encoded);
+}
+
+#[test]
+#[cfg(feature = "gfm")]
+fn comment_markdown() {
+    let comment = Comment {
+        value: "# Title\n\nsome *emphasis* and `code`\n\n+ one\n+ two".into(),
+    };
+    let html = comment.to_html();
+    assert!(
+        html.starts_with("<h1>Title</h1>\n<p>some <em>emphasis</em> and <code>code</code></p>")
+    );
+    let plain = comment.to_plain_text();
+    assert_eq!(plain, "Title\nsome emphasis and code\none\ntwo");
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn comment_reflow() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let arena = arena.builder();
+    let text = [
+        "a long line pasted from somewhere that goes on and on",
+        "and a short one",
+        "",
+        "```",
+        "code that is much longer than the width stays",
+        "```",
+        "- a list item that is also longer than the width",
+        "# Heading that is longer than the width",
+        "    indented code",
+        "supercalifragilistic word",
+    ];
+    let mut comment = Comment::from_lines(arena, text).unwrap();
+    comment.reflow(arena, 20).unwrap();
+    let expect = [
+        "a long line pasted",
+        "from somewhere that",
+        "goes on and on and a",
+        "short one",
+        "",
+        "```",
+        "code that is much longer than the width stays",
+        "```",
+        "- a list item that is also longer than the width",
+        "# Heading that is longer than the width",
+        "    indented code",
+        "supercalifragilistic",
+        "word",
+    ];
+    assert_eq!(comment.value.lines().collect::<Vec<_>>(), expect);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn comment_reflow_fences() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let arena = arena.builder();
+    let text = [
+        "~~~", "short", "lines", "~~~", "  ```", "stay", "apart", "  ```",
+    ];
+    let mut comment = Comment::from_lines(arena, text).unwrap();
+    comment.reflow(arena, 20).unwrap();
+    assert_eq!(comment.value.lines().collect::<Vec<_>>(), text);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn comment_reflow_rules() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let arena = arena.builder();
+    let text = [
+        "Title", "=====", "Part", "----", "one", "***", "two", "___", "three",
+    ];
+    let mut comment = Comment::from_lines(arena, text).unwrap();
+    comment.reflow(arena, 20).unwrap();
+    assert_eq!(comment.value.lines().collect::<Vec<_>>(), text);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn comment_reflow_list_continuation() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let arena = arena.builder();
+    let text = [
+        "- an item",
+        "  that goes on",
+        "  over lines",
+        "1. a number",
+        "   and more",
+        "after the",
+        "list",
+    ];
+    let mut comment = Comment::from_lines(arena, text).unwrap();
+    comment.reflow(arena, 20).unwrap();
+    let expect = [
+        "- an item",
+        "  that goes on",
+        "  over lines",
+        "1. a number",
+        "   and more",
+        "after the list",
+    ];
+    assert_eq!(comment.value.lines().collect::<Vec<_>>(), expect);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn comment_reflow_width() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let arena = arena.builder();
+    let mut comment = Comment::from_lines(arena, ["some text"]).unwrap();
+    assert_eq!(comment.reflow(arena, 0), Err("width of a line"));
+    assert_eq!(comment.value, "some text".into());
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn file_comments_located() {
+    use tindalwic::comments::Slot;
+    use tindalwic::walk::Branch;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("#!run\n#intro\n//doc\n[l]\n\t#p\n\tx\n\t#e\n");
+    let found: Vec<_> = file
+        .comments()
+        .map(|located| {
+            (
+                located.branches,
+                located.slot,
+                located.comment.value.to_string(),
+            )
+        })
+        .collect();
+    let l = Branch::Entry("l".into());
+    let expect = vec![
+        (vec![], Slot::Hashbang, "run".to_string()),
+        (vec![], Slot::Prolog, "intro".to_string()),
+        (vec![l], Slot::Before, "doc".to_string()),
+        (vec![l], Slot::Prolog, "p".to_string()),
+        (vec![l, Branch::Item(0)], Slot::Epilog, "e".to_string()),
+    ];
+    assert_eq!(found, expect);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn remove_comments_where() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let source = "#intro\n//INTERNAL: do not ship\na=1\n\n//public\n{b}\n\t#INTERNAL\n\tc=2\n\t#also INTERNAL\n";
+    let mut file = arena.panic_first_error(source);
+    let removed = file.remove_comments_where(&mut |_, _, comment| {
+        comment.value.lines().any(|line| line.contains("INTERNAL"))
+    });
+    assert_eq!(removed, 3);
+    assert_eq!(file.to_string(), "#intro\na=1\n\n//public\n{b}\n\tc=2\n");
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn documentation_coverage() {
+    use tindalwic::walk::Branch;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let source = "//the name\nname=x\n{server}\n\t#the server\n\tport=80\n\t{tls}\n\t\tkey=k\n";
+    let file = arena.panic_first_error(source);
+    let top = file.coverage(1);
+    assert_eq!((top.entries, top.dicts), (2, 0));
+    assert_eq!(top.undocumented, vec![vec![Branch::Entry("server".into())]]);
+    assert!(top.without_prolog.is_empty());
+    assert!(!top.is_complete());
+    let two = file.coverage(2);
+    assert_eq!((two.entries, two.dicts), (4, 1));
+    assert!(two.without_prolog.is_empty());
+    let all = file.coverage(usize::MAX);
+    assert_eq!((all.entries, all.dicts), (5, 2));
+    assert_eq!(all.undocumented.len(), 4);
+    let tls = vec![Branch::Entry("server".into()), Branch::Entry("tls".into())];
+    assert_eq!(all.without_prolog, vec![tls]);
+    assert!(file.coverage(0).is_complete());
+    let listed = arena.panic_first_error("//the servers\n[servers]\n\t{}\n\t\tport=80\n");
+    let two = listed.coverage(2);
+    assert_eq!((two.entries, two.dicts), (2, 1));
+    let dict = vec![Branch::Entry("servers".into()), Branch::Item(0)];
+    assert_eq!(two.without_prolog, vec![dict]);
+    assert_eq!(listed.coverage(1).dicts, 0);
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn reference_markdown() {
+    use tindalwic::render::Reference;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let source = "#All the settings.\n//who runs it\nuser=ops\n{server}\n\t#the server\n\tport=80\n\t[hosts]\n\t\ta\n\t\t{}\n\t\t\tb=``x\n";
+    let file = arena.panic_first_error(source);
+    let expect = [
+        "All the settings.",
+        "",
+        "# `user`",
+        "",
+        "who runs it",
+        "",
+        "```",
+        "ops",
+        "```",
+        "",
+        "# `server`",
+        "",
+        "the server",
+        "",
+        "## `port`",
+        "",
+        "```",
+        "80",
+        "```",
+        "",
+        "## `hosts`",
+        "",
+        "```",
+        "a",
+        "```",
+        "",
+        "### item 2",
+        "",
+        "#### `b`",
+        "",
+        "```",
+        "``x",
+        "```",
+        "",
+        "",
+    ];
+    assert_eq!(Reference(file).to_string(), expect.join("\n"));
+    let file = arena.panic_first_error("`a``b`=1\n");
+    let heading = Reference(file).to_string();
+    assert!(heading.starts_with("# ``` `a``b` ```\n"), "{heading}");
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn find_comments() {
+    use tindalwic::comments::Slot;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let source = "//deprecated: use b\na=1\n{b}\n\t#current\n\tc=2\n\t#Deprecated\n\t\tsoon\n";
+    let file = arena.panic_first_error(source);
+    let found: Vec<_> = file.find_comments("deprecated").collect();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].slot, Slot::Before);
+    assert_eq!(file.find_comments("d\nsoon").count(), 1);
+    assert_eq!(file.find_comments("missing").count(), 0);
+    #[cfg(feature = "regex")]
+    {
+        let regex = regex::Regex::new("(?i)^deprecated").unwrap();
+        assert_eq!(file.find_comments_matching(&regex).count(), 2);
+    }
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn gap_policy() {
+    use tindalwic::fmt::Gaps;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let source = "a=1\n\nb=2\n//c\nc=3\n{d}\n\te=4\n\t//f\n\tf=5\n";
+    let file = arena.panic_first_error(source);
+    file.cells.gap_before_commented_keys();
+    assert_eq!(
+        file.to_string(),
+        "a=1\n\nb=2\n\n//c\nc=3\n{d}\n\te=4\n\t//f\n\tf=5\n"
+    );
+    file.cells.set_gap("b".into(), false).unwrap();
+    assert_eq!(file.cells.set_gap("x".into(), true), Err("key not found"));
+    file.normalize_gaps(Gaps::DiffFriendly);
+    let expect = "a=1\n\nb=2\n\n//c\nc=3\n\n{d}\n\te=4\n\n\t//f\n\tf=5\n";
+    assert_eq!(file.to_string(), expect);
+    file.normalize_gaps(Gaps::Remove);
+    assert_eq!(
+        file.to_string(),
+        "a=1\nb=2\n//c\nc=3\n{d}\n\te=4\n\t//f\n\tf=5\n"
+    );
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn generated_header() {
+    use tindalwic::alloc::fill_template;
+    let template = "@generated by {tool} {version}\nDO NOT EDIT, run `{tool}` instead";
+    let header = fill_template(template, &[("tool", "gen"), ("version", "1.2")]).unwrap();
     assert_eq!(
-        File::try_from_dict_without_epilog(&Item::dict(entries))
-            .unwrap()
-            .to_string(),
-        "<key>\n\tone\n\ttwo\n"
+        header,
+        "@generated by gen 1.2\nDO NOT EDIT, run `gen` instead"
+    );
+    assert_eq!(fill_template("{x}", &[]), Err("unknown placeholder"));
+    assert_eq!(fill_template("{x", &[("x", "")]), Err("{ without }"));
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let mut file = arena.panic_first_error("#hand written\na=1\n");
+    let arena = arena.builder();
+    file.set_generated_header(arena, &header).unwrap();
+    let once = file.to_string();
+    let expect =
+        "#@generated by gen 1.2\n\tDO NOT EDIT, run `gen` instead\n\t\n\thand written\na=1\n";
+    assert_eq!(once, expect);
+    file.set_generated_header(arena, &header).unwrap();
+    assert_eq!(file.to_string(), once);
+    file.set_generated_header(arena, "@generated again")
+        .unwrap();
+    assert_eq!(
+        file.to_string(),
+        "#@generated again\n\t\n\thand written\na=1\n"
     );
+    assert!(file.set_generated_header(arena, "no marker").is_err());
+    assert!(file.set_generated_header(arena, "@generated\n\nx").is_err());
+    let mut bare = File::default();
+    bare.set_generated_header(arena, "@generated").unwrap();
+    assert_eq!(bare.to_string(), "#@generated\n");
 }
 
 #[test]
-fn multi_line_key() {
-    arena! {
-        let mut arena = <4dict,1list>;
-    }
-    let data = "@one\n\ttwo\n<>\n\tv\n@l\n\t\n[]\n@d\n\t\n{}\n";
-    let file = arena.panic_first_error(data);
-    assert_eq!(file.to_string(), data);
-    let report = &mut |err| {
-        print!("{err}");
-        tindalwic::parse::Reported::Continue
-    };
-    assert!(arena.report_errors("@", report).is_none());
-    assert!(arena.report_errors("@k", report).is_none());
-    assert!(arena.report_errors("@k\n", report).is_none());
-    assert!(arena.report_errors("@k\n<", report).is_none());
-    assert!(arena.report_errors("@k\n<>", report).is_some());
-    assert!(arena.report_errors("@k\n<x>", report).is_none());
+#[cfg(feature = "bumpalo")]
+fn comment_diff() {
+    use tindalwic::comments::{CommentChange, diff_comments};
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let old = arena.panic_first_error("//the a\na=1\n//the b\nb=2\n{c}\n\tx=1\n");
+    let new = arena.panic_first_error("//the a\na=9\n//THE B\nb=2\n{c}\n\t#new\n\tx=1\n");
+    let changes = diff_comments(&old, &new);
+    let expect = [
+        (
+            "{b} before".to_string(),
+            CommentChange::Changed("the b".into(), "THE B".into()),
+        ),
+        ("{c} prolog".to_string(), CommentChange::Added("new".into())),
+    ];
+    let changes = changes
+        .into_iter()
+        .map(|(key, change)| (key.to_string(), change));
+    assert_eq!(changes.collect::<Vec<_>>(), expect);
+    let changes = diff_comments(&new, &old);
+    let (_, change) = changes.into_iter().next_back().unwrap();
+    assert_eq!(change, CommentChange::Removed("new".into()));
+    assert!(diff_comments(&old, &old).is_empty());
+}
+
+#[test]
+fn value_raw_lines() {
+    let value = Value::slice_prefix(2, "ONE\n\t\tTWO\n\t\t\tTHREE");
+    assert_eq!(value.indent(), Some(2));
+    let raw: Vec<_> = value.raw_lines().collect();
+    assert_eq!(raw, ["ONE", "\t\tTWO", "\t\t\tTHREE"]);
+    let lines: Vec<_> = value.lines().collect();
+    assert_eq!(lines, ["ONE", "TWO", "\tTHREE"]);
+    let single = Value::from("just one");
+    assert_eq!(single.indent(), None);
+    assert_eq!(single.raw_lines().collect::<Vec<_>>(), ["just one"]);
 }
 
 #[test]
 #[cfg(feature = "bumpalo")]
-fn walk_error() {
+fn task_comments() {
+    use tindalwic::comments::Slot;
+    use tindalwic::walk::Branch;
     let bump = bumpalo::Bump::new();
     let mut arena = tindalwic::bumpalo::Arena::new(&bump);
-    let file = arena
-        .panic_first_error("[data]\n\tzero\n\t{}\n\t\tk=v")
-        .embed_without_hashbang();
-    path!({"data"}List).walk(file).unwrap();
-    path!({"data"}[0]List).walk(file).unwrap_err();
-    path!({"data"}[0]Text).walk(file).unwrap();
-    path!({"data"}[1]{"x"}Text).walk(file).unwrap_err();
-    path!({"data"}[1]{"k"}Text).walk(file).unwrap();
-    assert_eq!(
-        path!({"data"}[7]Text).walk(file).unwrap_err().to_string(),
-        "walk ({data}[7]): index out of bounds"
-    );
+    let source =
+        "#TODO: pick a license\n//port\n\tFIXME find a free one\n\tnot a TODOS\nport=80\n#XXX\n";
+    let file = arena.panic_first_error(source);
+    let found: Vec<_> = file
+        .task_comments(&["TODO", "FIXME", "XXX"])
+        .map(|task| (task.marker, task.text, task.branches, task.slot))
+        .collect();
+    let port = vec![Branch::Entry("port".into())];
+    let expect = vec![
+        ("TODO", "pick a license", vec![], Slot::Prolog),
+        ("FIXME", "find a free one", port.clone(), Slot::Before),
+        ("XXX", "", port, Slot::Epilog),
+    ];
+    assert_eq!(found, expect);
 }
+
 #[test]
-fn nested_lists() {
-    json! {
-        let items = [[[["value"]]]].unwrap();
-    }
-    let mut array = Entry::array::<1>();
-    array[0].get_mut().item = Item::list(items);
-    let file = File {
-        cells: &array[..],
-        ..Default::default()
+#[cfg(feature = "bumpalo")]
+fn numbers() {
+    use tindalwic::number::{
+        Exponent, Format, Precision, parse_f64, parse_i64, text_f64, text_i64,
     };
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let mut spell = |value: f64, format: Format| text_f64(arena.builder(), value, format).unwrap();
+    let shortest = Format::default();
+    for value in [
+        0.1,
+        -0.0,
+        1e21,
+        1e-7,
+        5e-324,
+        f64::MAX,
+        123456.789,
+        f64::INFINITY,
+    ] {
+        let text = spell(value, shortest);
+        assert_eq!(
+            parse_f64(&text).unwrap().to_bits(),
+            value.to_bits(),
+            "{value}"
+        );
+    }
+    assert!(parse_f64(&spell(f64::NAN, shortest)).unwrap().is_nan());
+    let spelled = [0.1, 1e21, 1e-7, -0.0, f64::NEG_INFINITY].map(|value| spell(value, shortest));
     assert_eq!(
-        file.to_string(),
-        "[]\n\t[]\n\t\t[]\n\t\t\t[]\n\t\t\t\tvalue\n"
+        spelled,
+        ["0.1", "1e21", "1e-7", "-0", "-inf"].map(Value::from)
     );
-    let cell = path!({""}[0][0][0][0]Text)
-        .walk(file.embed_without_hashbang())
-        .unwrap();
-    let Item::Text { value, .. } = cell.get() else {
-        unreachable!("this destructuring always succeeds because path walk did");
+    let fixed = Format {
+        precision: Precision::Digits(2),
+        exponent: Exponent::Never,
     };
-    assert_eq!(Vec::from_iter(value.lines()), vec!["value"]);
+    assert_eq!(spell(1e21, fixed), Value::from("1000000000000000000000.00"));
+    let always = Format {
+        precision: Precision::Digits(3),
+        exponent: Exponent::Always,
+    };
+    assert_eq!(spell(1234.5, always), Value::from("1.234e3"));
+    assert_eq!(text_i64(arena.builder(), -42).unwrap(), Value::from("-42"));
+    assert_eq!(parse_i64(&Value::from("-42")), Some(-42));
+    assert_eq!(parse_i64(&Value::from("1,000")), None);
+    assert_eq!(parse_f64(&Value::from("1\n2")), None);
 }
 
 #[test]
-fn nested_dicts() {
+fn hash_map() {
     json! {
-        let entries = {"1":"one","2":["two"],"a":{"b":{"c":{"d":{"k":"v"}}}}}.unwrap();
+        let entries = {"":"0","a":"1","b":"2","c\nc":"3"}.unwrap();
     }
-    let dict = Item::dict(entries);
-    let mut keys = Vec::new();
+    let mut map = HashMap::new();
     for entry in entries {
-        let entry = entry.get();
-        keys.push(entry.key.lines().next().unwrap_or(""));
+        let Entry { key, item, .. } = entry.get();
+        map.insert(key, item);
     }
-    assert_eq!(keys, vec!["1", "2", "a"]);
-    assert_eq!(
-        File::try_from_dict_without_epilog(&dict)
-            .unwrap()
-            .to_string(),
-        "1=one\n[2]\n\ttwo\n{a}\n\t{b}\n\t\t{c}\n\t\t\t{d}\n\t\t\t\tk=v\n"
-    );
-    let cell = path!({"a"}{"b"}{"c"}{"d"}{"k"}Text).walk(dict).unwrap();
-    let Item::Text { value, .. } = cell.get().item else {
-        unreachable!("this destructuring always succeeds because path walk did");
-    };
-    assert_eq!(Vec::from_iter(value.lines()), vec!["v"]);
+    assert_eq!(map.len(), entries.len());
 }
 
 #[test]
-fn change_in_list() {
-    json! {
-        let entries = {"a":{"b":["v"]}}.unwrap();
+#[cfg(feature = "bumpalo")]
+fn generated_documents_round_trip() {
+    use tindalwic::testing::generate;
+    assert_eq!(generate(7, 50), generate(7, 50));
+    assert_ne!(generate(7, 50), generate(8, 50));
+    for seed in 0..50 {
+        let content = generate(seed, 200);
+        let bump = bumpalo::Bump::new();
+        let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+        let file = arena.panic_first_error(&content);
+        assert_eq!(file.to_string(), content, "seed {seed}");
     }
-    let dict = Item::dict(entries);
-    let cell = path!({"a"}{"b"}[0]Text).walk(dict).unwrap();
-    let Item::Text { value, .. } = cell.get() else {
-        unreachable!("this destructuring always succeeds because path walk did");
-    };
-    let epilog = Comment::some("c");
-    cell.set(Item::Text { value, epilog });
-    assert_eq!(
-        File::try_from_dict_without_epilog(&dict)
-            .unwrap()
-            .to_string(),
-        "{a}\n\t[b]\n\t\tv\n\t\t#c\n"
-    );
 }
 
 #[test]
-fn change_in_dict() {
-    json! {
-        let entries = {"a":[{"b":"z"}]}.unwrap();
+#[cfg(feature = "bumpalo")]
+fn encode_chunks() {
+    use tindalwic::testing::generate;
+    for seed in 0..20 {
+        let content = generate(seed, 200);
+        let bump = bumpalo::Bump::new();
+        let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+        let file = arena.panic_first_error(&content);
+        assert_eq!(
+            file.encode_chunks().collect::<String>(),
+            content,
+            "seed {seed}"
+        );
     }
-    let dict = Item::dict(entries);
-    let cell = path!({"a"}[0]{"b"}Text).walk(dict).unwrap();
-    let mut entry = cell.get();
-    entry.item = Item::text("c");
-    cell.set(entry);
+    let content = "#intro\nk=value\n[l]\n\t<>\n\t\tmany\n\t\tlines\n";
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error(content);
+    let chunks: Vec<&str> = file.encode_chunks().collect();
     assert_eq!(
-        File::try_from_dict_without_epilog(&dict)
-            .unwrap()
-            .to_string(),
-        "[a]\n\t{}\n\t\tb=c\n"
+        chunks,
+        [
+            "#", "intro", "\n", "k", "=", "value", "\n", "[", "l", "]", "\n"
+        ]
+        .into_iter()
+        .chain(["\t", "<>\n", "\t", "\t", "many\n\t\tlines", "\n"])
+        .collect::<Vec<_>>()
     );
+    // the multi-line text is borrowed from the content, not copied
+    let range = content.as_bytes().as_ptr_range();
+    assert!(range.contains(&chunks[15].as_ptr()));
 }
 
-#[test]
-fn inject_comments() {
-    json! {
-        let entries = {"k":"v"}.unwrap();
+#[cfg(feature = "proptest")]
+mod properties {
+    use proptest::prelude::*;
+    use tindalwic::testing::{assert_roundtrip, file};
+
+    proptest! {
+        #[test]
+        fn encode_after_parse_is_identity(source in file()) {
+            assert_roundtrip(&source);
+        }
     }
-    let dict = Item::dict(entries);
-    let cell = path!({"k"}Text).walk(dict).unwrap();
-    let mut entry = cell.get();
-    let Item::Text { value, .. } = entry.item else {
-        unreachable!("this destructuring always succeeds because path walk did");
+}
+
+#[test]
+#[cfg(all(feature = "std", feature = "bumpalo"))]
+fn save_atomic() {
+    let dir = std::env::temp_dir().join(format!("tindalwic-save-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.tindalwic");
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let first = arena.panic_first_error("version=1\n");
+    let second = arena.panic_first_error("version=2\n");
+    first.save_atomic(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "version=1\n");
+    second.save_atomic_with_backup(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "version=2\n");
+    let bak = dir.join("app.tindalwic.bak");
+    assert_eq!(std::fs::read_to_string(&bak).unwrap(), "version=1\n");
+    let mut names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["app.tindalwic", "app.tindalwic.bak"]); // no temporary left
+    assert!(first.save_atomic(dir.join("missing").join("x")).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg(all(feature = "std", feature = "bumpalo"))]
+fn save_atomic_concurrently() {
+    let dir = std::env::temp_dir().join(format!("tindalwic-race-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.tindalwic");
+    std::thread::scope(|scope| {
+        for thread in 0..4 {
+            let path = &path;
+            scope.spawn(move || {
+                let bump = bumpalo::Bump::new();
+                let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+                let content = format!("thread={thread}\n");
+                let file = arena.panic_first_error(&content);
+                for _ in 0..20 {
+                    file.save_atomic(path).unwrap();
+                }
+            });
+        }
+    });
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(saved.starts_with("thread="), "{saved}");
+    let names: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(names.len(), 1); // no temporary left
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg(all(feature = "std", feature = "bumpalo"))]
+fn save_with_checksum() {
+    use tindalwic::std::SaveOptions;
+    let dir = std::env::temp_dir().join(format!("tindalwic-sum-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.tindalwic");
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let options = SaveOptions {
+        checksum: true,
+        verify: true,
+        ..SaveOptions::default()
     };
-    let epilog = Comment::some("c");
-    entry.before = Comment::some("b");
-    entry.item = Item::Text { value, epilog };
-    cell.set(entry);
+    let short = arena.panic_first_error("version=1\n");
+    short.save_with(&path, options).unwrap();
+    let sum = std::fs::read_to_string(dir.join("app.tindalwic.sha256")).unwrap();
+    let expected = "2815beccc71f868badea754664ff3c46f4fb78c1e0ac396e73a965d0f56054a1";
+    assert_eq!(sum, format!("{expected}  app.tindalwic\n"));
+    let long = format!("long={}\n", "x".repeat(100)); // more than one block
+    let long = arena.panic_first_error(bump.alloc_str(&long));
+    let options = SaveOptions {
+        sync: false,
+        ..options
+    };
+    long.save_with(&path, options).unwrap();
+    let sum = std::fs::read_to_string(dir.join("app.tindalwic.sha256")).unwrap();
+    let expected = "a67c815521c0c015f4f168cd681457d1bc45d26353ad30296e96b50a24dae3e5";
+    assert_eq!(sum, format!("{expected}  app.tindalwic\n"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[cfg(all(feature = "std", feature = "bumpalo"))]
+fn easy() {
+    use tindalwic::easy::{Document, load};
+    let dir = std::env::temp_dir().join(format!("tindalwic-easy-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.tindalwic");
+    std::fs::write(&path, "{server}\n\t//the port\n\tport=80\n\t#see RFC\n").unwrap();
+    let mut config = load(&path).unwrap();
+    assert_eq!(config.get_str(&["server", "port"]).as_deref(), Some("80"));
+    assert_eq!(config.get_str(&["server", "host"]), None);
+    assert_eq!(config.get_str(&["server"]), None); // a dict
+    config.set_str(&["server", "port"], "8080").unwrap();
+    config
+        .set_str(&["server", "tls", "cert"], "two\nlines")
+        .unwrap();
+    config.set_str(&["name"], "app").unwrap();
+    let error = config.set_str(&["name", "first"], "x").unwrap_err();
+    assert_eq!(error.kind(), tindalwic::ErrorKind::Path);
+    assert_eq!(error.to_string(), "walk (step 0): not a dict");
+    let error = config.set_str(&[], "x").unwrap_err();
+    assert_eq!(error.kind(), tindalwic::ErrorKind::Usage);
+    config.save(&path).unwrap();
+    let expected = "{server}\n\t//the port\n\tport=8080\n\t#see RFC\n\t{tls}\n\t\t<cert>\n\t\t\ttwo\n\t\t\tlines\nname=app\n";
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
     assert_eq!(
-        File::try_from_dict_without_epilog(&dict)
+        load(&path)
             .unwrap()
-            .to_string(),
-        "//b\nk=v\n#c\n"
+            .get_str(&["server", "tls", "cert"])
+            .as_deref(),
+        Some("two\nlines")
     );
+    let error = Document::parse("bad", "[unclosed\n".into()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    std::fs::remove_dir_all(&dir).unwrap();
 }
 
 #[test]
-fn change_structure() {
-    let key = "k";
-    json! {
-        let entries = {key:["v"]}.unwrap();
-    }
-    let dict = Item::dict(entries);
-    let cell = path!({key}[0]Text).walk(dict).unwrap();
-    let Item::Text { value, .. } = cell.get() else {
-        unreachable!("this destructuring always succeeds because path walk did");
+#[cfg(feature = "bumpalo")]
+fn reused_encoder() {
+    use tindalwic::alloc::Encoder;
+    use tindalwic::fmt::EncodeOptions;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let big = arena.panic_first_error("b=2\n\na=1\n");
+    let small = arena.panic_first_error("x=y\n");
+    let mut encoder = Encoder::default();
+    assert_eq!(encoder.encode(&big), "b=2\n\na=1\n");
+    let buffer = encoder.encode(&big).as_ptr();
+    assert_eq!(encoder.encode(&small), "x=y\n");
+    assert_eq!(encoder.encode(&small).as_ptr(), buffer); // the same allocation
+    encoder.options = EncodeOptions::canonical();
+    assert_eq!(encoder.encode(&big), "a=1\nb=2\n");
+    let mut encoder = Encoder::new(EncodeOptions::canonical());
+    assert_eq!(encoder.encode(&big), "a=1\nb=2\n");
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn with_hashbang() {
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let file = arena.panic_first_error("#about\nport=1\n");
+    let script = file.with_hashbang("/usr/bin/env mytool").unwrap();
+    let encoded = "#!/usr/bin/env mytool\n#about\nport=1\n";
+    assert_eq!(script.to_string(), encoded);
+    assert_eq!(arena.panic_first_error(encoded), script);
+    assert!(file.with_hashbang("two\nlines").is_err());
+    assert!(file.with_hashbang("with\ttab").is_err());
+    assert!(file.with_hashbang("!bang").is_err());
+}
+
+#[test]
+#[cfg(feature = "bumpalo")]
+fn strip_comments() {
+    use tindalwic::fmt::EncodeOptions;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let content = "#!/usr/bin/env tool\n#about\n//port\nport=1\n#after\n\n[list]\n\t#intro\n\titem\n\t#after item\n";
+    let file = arena.panic_first_error(content);
+    let options = EncodeOptions {
+        strip_comments: true,
+        ..EncodeOptions::default()
     };
-    let b = String::from("b");
-    let epilog = Comment::some(&b);
-    json! {
-        let patch = {"p":(Item::Text { value, epilog })}.unwrap();
-    }
-    cell.set(Item::dict(patch));
-    assert_eq!(
-        File::try_from_dict_without_epilog(&dict)
-            .unwrap()
-            .to_string(),
-        "[k]\n\t{}\n\t\tp=v\n\t\t#b\n"
-    )
+    let stripped = "#!/usr/bin/env tool\nport=1\n[list]\n\titem\n";
+    assert_eq!(file.encode_with(options), stripped);
+    assert_eq!(file.to_string(), content); // the tree is untouched
 }
 
 #[test]
-fn hash_map() {
-    json! {
-        let entries = {"":"0","a":"1","b":"2","c\nc":"3"}.unwrap();
-    }
-    let mut map = HashMap::new();
-    for entry in entries {
-        let Entry { key, item, .. } = entry.get();
-        map.insert(key, item);
-    }
-    assert_eq!(map.len(), entries.len());
+#[cfg(feature = "bumpalo")]
+fn clone_owned() {
+    use tindalwic::parse::Parse;
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    let copy = {
+        let source = String::from("#intro\n{a}\n\t//b\n\t<b>\n\t\tone\n\t\ttwo\n\t#after\n");
+        let bump = bumpalo::Bump::new();
+        let mut scratch = tindalwic::bumpalo::Arena::new(&bump);
+        let file = scratch.panic_first_error(&source);
+        file.clone_owned(arena.builder()).unwrap()
+    }; // the source and the scratch arena are gone
+    let encoded = "#intro\n{a}\n\t//b\n\t<b>\n\t\tone\n\t\ttwo\n\t#after\n";
+    assert_eq!(copy.to_string(), encoded);
+    // a duplicate under another key, then changed without touching the original
+    let original = copy.cells[0].get();
+    let mut duplicate = original;
+    duplicate.key = "z".into();
+    duplicate.item = original.item.clone_owned(arena.builder()).unwrap();
+    let Item::Dict { cells, .. } = duplicate.item else {
+        panic!()
+    };
+    let mut inner = cells[0].get();
+    inner.item = Item::text("changed");
+    cells[0].set(inner);
+    assert_eq!(copy.to_string(), encoded);
+    assert!(duplicate.to_string().contains("b=changed"));
+}
+
+#[test]
+#[cfg(all(feature = "std", feature = "bumpalo"))]
+fn append_entry() {
+    use tindalwic::alloc::encode_append;
+    use tindalwic::std::append_entry_to_file;
+    let entry = Entry {
+        gap: true,
+        before: Comment::some("at noon"),
+        key: "event".into(),
+        item: Item::text("lunch"),
+    };
+    let mut existing = String::from("#log\nevent=start\n[tags]\n\tx");
+    encode_append(&entry, &mut existing);
+    let expected = "#log\nevent=start\n[tags]\n\tx\n\n//at noon\nevent=lunch\n";
+    assert_eq!(existing, expected);
+    let bump = bumpalo::Bump::new();
+    let mut arena = tindalwic::bumpalo::Arena::new(&bump);
+    assert_eq!(arena.panic_first_error(&existing).cells.len(), 3);
+
+    let path = std::env::temp_dir().join(format!("tindalwic-append-{}", std::process::id()));
+    std::fs::write(&path, "#log\nevent=start\n[tags]\n\tx").unwrap();
+    append_entry_to_file(&path, &entry).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+    std::fs::remove_file(&path).unwrap();
 }
 
 #[test]
@@ -529,6 +3010,42 @@ mod parse_err {
         );
     }
     #[test]
+    fn trailing_content() {
+        let bump = Bump::new();
+        let mut arena = HeapArena::new(&bump);
+        assert_eq!(
+            arena.first_error("k=v\nzzz"),
+            Err(ParseError::at(2, "missing `=` in dict"))
+        );
+        assert_eq!(
+            arena.first_error("k=v\n//"),
+            Err(ParseError::at(3, "gap/before but no key"))
+        );
+        assert_eq!(
+            arena.first_error("k=v\n#epilog\n#stray"),
+            Err(ParseError::at(3, "stray `#` comment"))
+        );
+    }
+    #[test]
+    fn complete_prefix() {
+        use tindalwic::parse::complete_prefix;
+        let content = "#p\nk=v\n#e\n\n//c\n\tmore\n[l]\n\tx";
+        let prefix = complete_prefix(content);
+        assert_eq!(&content[..prefix], "#p\nk=v\n#e\n");
+        let rest = &content[prefix..];
+        assert_eq!(complete_prefix(rest), 0);
+        assert_eq!(complete_prefix("{d}\n\ta=1\n\n\tb=2\nc"), 15);
+        assert_eq!(complete_prefix("a=1\n@b\n\tc\n<>\n\tv"), 4);
+        let bump = Bump::new();
+        let mut arena = HeapArena::new(&bump);
+        let head = arena.first_error(&content[..prefix]).unwrap();
+        let tail = arena.first_error(rest).unwrap();
+        let whole = arena.first_error(content).unwrap();
+        assert_eq!(head.prolog, whole.prolog);
+        assert_eq!(head.cells[0], whole.cells[0]);
+        assert_eq!(tail.cells[0], whole.cells[1]);
+    }
+    #[test]
     fn dict_errors() {
         let bump = Bump::new();
         let mut arena = HeapArena::new(&bump);
@@ -547,4 +3064,23 @@ mod parse_err {
             )
         );
     }
+    #[test]
+    fn end_of_line_comments() {
+        let bump = Bump::new();
+        let mut arena = HeapArena::new(&bump);
+        let content = "{a} #why\n<b>\t# why\n[c] x\n[d]\n\t{} #why";
+        let errors = arena
+            .collect_errors(content, usize::MAX)
+            .expect_err("invalid");
+        let eol = "no end-of-line comments (a `#` must start its own line)";
+        assert_eq!(
+            errors,
+            vec!(
+                ParseError::at(1, eol),
+                ParseError::at(2, eol),
+                ParseError::at(3, "malformed `[key]` in dict"),
+                ParseError::at(5, eol),
+            )
+        );
+    }
 }